@@ -0,0 +1,157 @@
+use std::{collections::HashMap, fmt::Display};
+
+use crate::{consts::*, registry};
+
+/// What a Zulip user is allowed to do with Status Bot. Every role can run the commands of the
+/// roles before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    User,
+    Faculty,
+    Maintainer,
+}
+
+impl Role {
+    fn parse(input: &str) -> Option<Role> {
+        match input.trim().to_lowercase().as_str() {
+            "user" => Some(Role::User),
+            "faculty" => Some(Role::Faculty),
+            "maintainer" => Some(Role::Maintainer),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Role::User => write!(f, "user"),
+            Role::Faculty => write!(f, "faculty"),
+            Role::Maintainer => write!(f, "maintainer"),
+        }
+    }
+}
+
+/// Roles required by default, every command not listed here can be run by anyone
+const DEFAULT_COMMAND_ROLES: &[(&str, Role)] = &[
+    ("admin", Role::Maintainer),
+    ("team_add", Role::Faculty),
+    ("team_remove", Role::Faculty),
+];
+
+/// Maps Zulip user ids to roles, and commands to the role they require
+#[derive(Debug, Default)]
+pub struct Policy {
+    /// [Zulip user id] -> [Role]. Anyone missing is a [`Role::User`]
+    roles: HashMap<u64, Role>,
+    /// [Command name] -> [Role]. Any command missing can be run by a [`Role::User`]
+    command_roles: HashMap<String, Role>,
+}
+
+impl Policy {
+    /// Builds a policy from comma separated lists of Zulip user ids (ZULIP_BOT_MAINTAINERS and
+    /// ZULIP_BOT_FACULTY) and a comma separated list of `command=role` overrides (COMMAND_ROLES)
+    pub fn new(maintainers: &str, faculty: &str, overrides: &str) -> Result<Policy, String> {
+        let mut roles = HashMap::new();
+        // Maintainers are inserted last so they keep the highest role when listed twice
+        for (list, role) in [(faculty, Role::Faculty), (maintainers, Role::Maintainer)] {
+            for id in list.split(COMMA).map(str::trim).filter(|id| !id.is_empty()) {
+                let id = id
+                    .parse()
+                    .map_err(|_| format!("'{id}' is not a Zulip user id"))?;
+                roles.insert(id, role);
+            }
+        }
+        let mut command_roles: HashMap<String, Role> = DEFAULT_COMMAND_ROLES
+            .iter()
+            .map(|(command, role)| (command.to_string(), *role))
+            .collect();
+        for entry in overrides.split(COMMA).filter(|e| !e.trim().is_empty()) {
+            let (command, role) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("'{entry}' should look like command=role"))?;
+            let command = command.trim().to_lowercase();
+            if !is_command(&command) {
+                return Err(format!("'{command}' is not a command"));
+            }
+            let role = Role::parse(role).ok_or_else(|| format!("'{role}' is not a role"))?;
+            command_roles.insert(command, role);
+        }
+        Ok(Policy {
+            roles,
+            command_roles,
+        })
+    }
+
+    pub fn role_of(&self, zulip_id: u64) -> Role {
+        self.roles.get(&zulip_id).copied().unwrap_or(Role::User)
+    }
+
+    pub fn required_role(&self, command: &str) -> Role {
+        self.command_roles
+            .get(command)
+            .copied()
+            .unwrap_or(Role::User)
+    }
+
+    /// Checks whether a Zulip user may run a command. Returns the missing role when they may not
+    pub fn authorize(&self, zulip_id: u64, command: &str) -> Result<(), Role> {
+        let required = self.required_role(command);
+        match self.role_of(zulip_id) >= required {
+            true => Ok(()),
+            false => Err(required),
+        }
+    }
+}
+
+/// Whether a role can be required for the command, e.g. `team_add` or `status_for`. Sub commands
+/// of `admin` are all authorized as `admin`
+fn is_command(name: &str) -> bool {
+    registry::COMMANDS.iter().any(|spec| {
+        spec.name == name
+            || (spec.name != "admin"
+                && spec
+                    .subcommands
+                    .iter()
+                    .any(|sub| format!("{}_{}", spec.name, sub.name) == name))
+    })
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Tests                                   */
+/* -------------------------------------------------------------------------- */
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::{Policy, Role};
+
+    #[test_case(1, "status" => Ok(()) ; "test anyone can set a status")]
+    #[test_case(3, "admin" => Err(Role::Maintainer) ; "test user cannot run admin")]
+    #[test_case(2, "admin" => Err(Role::Maintainer) ; "test faculty cannot run admin")]
+    #[test_case(1, "admin" => Ok(()) ; "test maintainer can run admin")]
+    #[test_case(2, "team_add" => Ok(()) ; "test faculty can manage teams")]
+    #[test_case(3, "team_add" => Err(Role::Faculty) ; "test user cannot manage teams")]
+    #[test_case(1, "team_add" => Ok(()) ; "test maintainer inherits faculty commands")]
+    #[test_case(3, "digest" => Err(Role::Faculty) ; "test override requires a role")]
+    #[test_case(3, "team_remove" => Ok(()) ; "test override lowers a role")]
+    #[test_case(3, "status_for" => Err(Role::Faculty) ; "test override of a sub command")]
+    fn test_authorize(zulip_id: u64, command: &str) -> Result<(), Role> {
+        let policy = Policy::new(
+            "1",
+            " 2, 1",
+            "digest=faculty, Team_Remove=User, status_for=faculty",
+        )
+        .unwrap();
+        policy.authorize(zulip_id, command)
+    }
+
+    #[test_case("abc", "" ; "test bad user id")]
+    #[test_case("", "admin" ; "test override without role")]
+    #[test_case("", "admin=root" ; "test unknown role")]
+    #[test_case("", "admn_stats=user" ; "test unknown command")]
+    #[test_case("", "admin_stats=user" ; "test admin sub command")]
+    fn test_invalid_policy(maintainers: &str, overrides: &str) {
+        assert!(Policy::new(maintainers, "", overrides).is_err());
+    }
+}
@@ -0,0 +1,58 @@
+//! Generates the Zulip emoji table (`src/zulip.json`) from Zulip's `emoji_codes.json`
+//!
+//! `emoji_codes.json` is built by Zulip's `tools/setup/emoji/build_emoji` and can be found in
+//! `static/generated/emoji/` of a Zulip checkout. Run this again after a Zulip release adds new
+//! emojis:
+//!
+//! ```sh
+//! cargo run --bin generate_emoji -- path/to/emoji_codes.json > src/zulip.json
+//! ```
+use std::{collections::BTreeMap, env, fs, process};
+
+use serde::Deserialize;
+
+/// The part of `emoji_codes.json` Status Bot needs
+#[derive(Deserialize)]
+struct EmojiCodes {
+    /// [Zulip alias] -> [Hex codepoints separated by `-`], e.g. `woman_technologist` ->
+    /// `1f469-200d-1f4bb`
+    name_to_codepoint: BTreeMap<String, String>,
+}
+
+/// Turns `1f469-200d-1f4bb` into 👩‍💻
+fn to_unicode(codepoints: &str) -> Option<String> {
+    codepoints
+        .split('-')
+        .map(|hex| u32::from_str_radix(hex, 16).ok().and_then(char::from_u32))
+        .collect()
+}
+
+fn main() {
+    let Some(path) = env::args().nth(1) else {
+        eprintln!("Usage: generate_emoji <emoji_codes.json>");
+        process::exit(1);
+    };
+    let file = fs::read_to_string(&path).unwrap_or_else(|e| {
+        eprintln!("Failed to read {path}: {e}");
+        process::exit(1);
+    });
+    let codes: EmojiCodes = serde_json::from_str(&file).unwrap_or_else(|e| {
+        eprintln!("{path} is not a Zulip emoji_codes.json: {e}");
+        process::exit(1);
+    });
+
+    let mut table = BTreeMap::new();
+    for (name, codepoints) in codes.name_to_codepoint {
+        match to_unicode(&codepoints) {
+            Some(unicode) => {
+                table.insert(name, unicode);
+            }
+            None => eprintln!("Skipping :{name}:, {codepoints} is not a unicode codepoint"),
+        }
+    }
+    eprintln!("Generated {} emoji aliases", table.len());
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&table).expect("a map of strings serializes")
+    );
+}
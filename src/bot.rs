@@ -1,19 +1,35 @@
+use std::collections::{BTreeSet, HashMap};
 use std::fmt::Display;
-use std::str::SplitWhitespace;
 use std::sync::{Arc, RwLock};
-use std::{collections::HashMap, env};
 
-use crate::rc::{UpdateBotRequest, UpdateBotResponse};
+use arc_swap::ArcSwap;
+
+use crate::rc::UpdateBotRequest;
 use crate::{
+    auth::{Policy, Role},
+    config::Config,
     consts::*,
+    delegation::{Delegation, Delegations, Mention},
+    desk_choice::{self, DeskChoices, DeskMatch},
+    digest::{DigestConfig, OptIns},
+    emoji::{self, EmojiFallbacks, EmojiTable},
+    history::{self, ExportFormat, History, HistoryEntry, Source},
+    logging,
+    pairing::{parse_pair_args, Beacon, Beacons},
+    parser::{resolve_alias, suggest, Args, ParseError},
     rc::{Desk, Position, RecurseClient},
+    registry::{find, render_command_help, render_help, CommandSpec},
     secret::Secret,
-    zulip::{OutgoingWebhook, Trigger, ZulipEmoji},
+    stats::Stats,
+    store::{self, Store},
+    team::{render_table, Groups},
+    validation::{self, Target, ValidationError},
+    zulip::{OutgoingWebhook, Trigger, ZulipClient, ZulipEmoji},
     HttpsClient, Result,
 };
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use time::{format_description::well_known::Iso8601, Duration, OffsetDateTime};
+use time::{format_description::well_known::Iso8601, Duration, OffsetDateTime, Time};
 
 /// Maybe(Emoji) , Maybe(Status), Maybe(ExpiresAt)
 type StatusParts = (Option<String>, Option<String>, Option<OffsetDateTime>);
@@ -23,80 +39,118 @@ type StatusParts = (Option<String>, Option<String>, Option<OffsetDateTime>);
 pub struct Bot {
     /// A Hyper HttpsClient
     client: HttpsClient,
-    /// A [`ZulipEmoji`] HashMap from zulip emoji alias to standard emoji alias
-    emojis: ZulipEmoji,
-    /// An inverted [`ZulipEmoji`] HashMap from unicode grapheme to zulip emoji aliases
-    emojis_inv: ZulipEmoji,
-    /// [Virtual RC Owner.Name] -> [Virtual RC Desk.ID]
+    /// Zulip emoji aliases and their unicode emojis, replaced by `admin reload_emojis`
+    emojis: RwLock<EmojiTable>,
+    /// Path or URL of the emoji table. None uses the table compiled into the binary
+    emoji_source: Option<String>,
+    /// [Virtual RC Owner.Name] -> [Virtual RC Desk.ID], every desk owned by the name sorted by id
     ///
     /// Zuliup usernames are used to looup in this table. Maybe not be a perfect match
-    pub desk_owners: Arc<RwLock<HashMap<String, (usize, Position)>>>,
+    ///
+    /// Refreshing swaps in a new snapshot, so lookups never wait for or miss because of a refresh
+    pub desk_owners: ArcSwap<HashMap<String, Vec<(usize, Position)>>>,
+    /// Every owned desk from the latest GET /api/desks, used to show the statuses of other people
+    pub desks: ArcSwap<Vec<Desk>>,
+    /// Held while a lookup refreshes stale desks, so concurrent misses share one GET /api/desks
+    desk_refresh: tokio::sync::Mutex<()>,
     /// Manually set the Virtual RC name associated with this Zulip username
     ///
     /// [Zulip Username] -> [Virtual RC Username]
-    pub corrected_names: Arc<RwLock<HashMap<String, String>>>,
+    pub corrected_names: ArcSwap<HashMap<String, String>>,
+    /// Bot managed groups of Virtual RC usernames used by the `team` command
+    groups: Store<Groups>,
+    /// Pairing beacons lit with the `pair` command
+    beacons: Store<Beacons>,
+    /// Who may set the status of someone else's desk with `status for`
+    delegations: Store<Delegations>,
+    /// The desk chosen with `desk` by people whose name owns several desks
+    desk_choices: Store<DeskChoices>,
+    /// Names of the custom emojis of the Zulip realm, from the latest GET /api/v1/realm/emoji
+    custom_emojis: Arc<RwLock<BTreeSet<String>>>,
+    /// The unicode emoji shown on Virtual RC desks for custom emojis, set with
+    /// `admin emoji_fallback`
+    emoji_fallbacks: Store<EmojiFallbacks>,
+    /// Every status applied through Status Bot
+    history: History,
+    /// Where and when to post the daily digest. None when the digest is disabled
+    digest: Option<DigestConfig>,
+    /// Desks whose owners opted in to the daily digest
+    digest_opt_ins: Store<OptIns>,
     /// An instance of a Virtual RC HTTP Client
     rc: RecurseClient,
-    /// The Bot's email used as a username for Zulip API requests
-    /// E.g. status1-bot@zulipchat.com
-    email: Secret,
-    /// The Zulip Bot's api key
-    api_key: Secret,
+    /// An instance of a Zulip HTTP Client
+    zulip: ZulipClient,
     /// A string of alphanumeric characters that can be used to authenticate the webhook request
     /// (each bot user uses a fixed token). You can get the token used by a given outgoing webhook
     /// bot in the zuliprc file downloaded when creating the bot.
     api_token: Secret,
-    /// The URL of the Zulip Instance
-    /// E.g. https://<subdoamin>.zulipchat.com
-    site: Secret,
+    /// Streams (by name or id) in which Status Bot answers mentions.
+    ///
+    /// None means Status Bot answers mentions in every stream it is subscribed to
+    stream_allowlist: Option<Vec<String>>,
     /// Home position of the Bot
     home: Position,
+    /// Who may run which commands
+    policy: Policy,
+    /// Counters shown by `admin stats`
+    stats: Stats,
+    /// Status commands only preview statuses, and nothing is changed in Virtual RC or posted to
+    /// Zulip besides replies. Used by staging deployments
+    dry_run: bool,
 }
 
 impl Bot {
+    /// The default duration of a pairing beacon (when none is provided)
+    const DEFAULT_PAIR_DURATION: Duration = Duration::new(3600 /* 1 hour */, 0);
+    /// How many emojis `emoji` lists
+    const EMOJI_SEARCH_RESULTS: usize = 10;
+    /// How many emojis are suggested for an unknown emoji alias
+    const EMOJI_SUGGESTIONS: usize = 3;
+
     /// Creates a new Status Bot instance
-    pub fn new(client: HttpsClient, emojis: ZulipEmoji) -> Bot {
-        let rc = RecurseClient::new(client.clone());
-        let desk_owners = Arc::new(RwLock::new(HashMap::new()));
-        let corrected_names = Arc::new(RwLock::new(HashMap::new()));
-        let email = env::var(ZULIP_BOT_EMAIL).expect("ZULIP_BOT_EMAIL is not set in the .env file");
-        let api_key =
-            env::var(ZULIP_BOT_API_KEY).expect("ZULIP_BOT_API_KEY is not set in the .env file");
-        let api_token =
-            env::var(ZULIP_BOT_API_TOKEN).expect("ZULIP_BOT_API_TOKEN is not set in the .env file");
-        let site = env::var(ZULIP_SITE).expect("ZULIP_SITE is not set in the .env file");
-        let home_x = env::var(BOT_HOME_X).expect("RC_BOT_HOME_X is not set in the .env file");
-        let home_y = env::var(BOT_HOME_Y).expect("RC_BOT_HOME_Y is not set in the .env file");
-        let x: usize = home_x.parse().expect("RC_BOT_HOME_X must be a number");
-        let y: usize = home_y.parse().expect("RC_BOT_HOME_Y must be a number");
-        let home = Position { x, y };
-        // This is a janky inversion of the emoji hashmap so instead of sending unicode back to
-        // Zulip, we send the Zulip emoji alias
-        let emojis_inv = ZulipEmoji(HashMap::from_iter(
-            emojis
-                .0
-                .iter()
-                .filter(|(_z_alias, u_alias)| emojic::parse_alias(u_alias).is_some())
-                .map(|(z_alias, u_alias)| {
-                    (
-                        emojic::parse_alias(u_alias).unwrap().grapheme.to_string(),
-                        format!(":{z_alias}:"),
-                    )
-                }),
-        ));
+    pub fn new(client: HttpsClient, emojis: ZulipEmoji, config: Config) -> Bot {
+        // Before any store is opened
+        store::set_data_dir(config.data_dir);
+        let rc = RecurseClient::new(client.clone(), &config.rc);
+        let zulip = ZulipClient::new(client.clone(), &config.zulip);
+        let desk_owners = ArcSwap::from_pointee(HashMap::new());
+        let desks = ArcSwap::from_pointee(Vec::new());
+        let corrected_names = ArcSwap::from_pointee(HashMap::new());
+        let groups = Store::open(GROUPS_FILE);
+        let beacons = Store::open(BEACONS_FILE);
+        let delegations = Store::open(DELEGATIONS_FILE);
+        let desk_choices = Store::open(DESK_CHOICES_FILE);
+        let custom_emojis = Arc::new(RwLock::new(BTreeSet::new()));
+        let emoji_fallbacks = Store::open(EMOJI_FALLBACKS_FILE);
+        let history = History::open(config.history_retention_days);
+        let digest_opt_ins = Store::open(DIGEST_OPT_INS_FILE);
+        let emojis = RwLock::new(EmojiTable::new(&emojis));
 
         Bot {
             client,
             emojis,
-            emojis_inv,
+            emoji_source: config.emoji_table,
             desk_owners,
+            desks,
+            desk_refresh: tokio::sync::Mutex::new(()),
             corrected_names,
+            groups,
+            beacons,
+            delegations,
+            desk_choices,
+            custom_emojis,
+            emoji_fallbacks,
+            history,
+            digest: config.digest,
+            digest_opt_ins,
             rc,
-            email: Secret(email.into()),
-            api_key: Secret(api_key.into()),
-            api_token: Secret(api_token.into()),
-            site: Secret(site.into()),
-            home,
+            zulip,
+            api_token: config.zulip.api_token,
+            stream_allowlist: config.stream_allowlist,
+            home: config.rc.home,
+            policy: config.policy,
+            stats: Stats::new(),
+            dry_run: config.dry_run,
         }
     }
 
@@ -115,9 +169,15 @@ impl Bot {
         let desks = self.rc.get_desks().await?;
 
         debug!("bot -> cache_desk_owners -> GET desks");
+        self.store_desks(desks.0);
+        self.stats.record_desk_refresh(OffsetDateTime::now_utc());
 
+        Ok(())
+    }
+
+    /// Swaps in new snapshots of the desk owners and the owned desks
+    fn store_desks(&self, desks: Vec<Desk>) {
         let x = desks
-            .0
             .iter()
             .filter(|desk| desk.owner.is_some())
             .map(|desk| {
@@ -128,15 +188,53 @@ impl Bot {
                 (owner_name, desk_id, pos)
             })
             .fold(HashMap::new(), |mut map, (name, desk_id, pos)| {
-                let _ = map.insert(name.clone(), (desk_id, pos));
+                // Names are not unique, and one person may own several desks
+                map.entry(name.clone())
+                    .or_insert_with(Vec::new)
+                    .push((desk_id, pos));
                 map
             });
+        let x = x
+            .into_iter()
+            .map(|(name, mut desks): (String, Vec<(usize, Position)>)| {
+                desks.sort_by_key(|(desk_id, _)| *desk_id);
+                (name, desks)
+            })
+            .collect();
+
+        self.desk_owners.store(Arc::new(x));
+        debug!("bot -> store_desks -> updated desk_owners");
+
+        let owned_desks = desks
+            .into_iter()
+            .filter(|desk| desk.owner.is_some())
+            .collect();
+        self.desks.store(Arc::new(owned_desks));
+    }
 
-        if let Ok(mut d_o) = self.desk_owners.write() {
-            *d_o = x;
-            debug!("bot -> cache_desk_owners -> successfully got the write lock for desk_owners and updated");
+    /// Loads the emoji table from EMOJI_TABLE, or the table compiled into the binary when it is not
+    /// set. Returns the number of Zulip aliases loaded
+    pub async fn reload_emojis(&self) -> Result<usize> {
+        let emojis = match &self.emoji_source {
+            Some(source) => emoji::load_table(&self.client, source).await?,
+            None => emoji::bundled_table(),
+        };
+        let count = emojis.0.len();
+        let table = EmojiTable::new(&emojis);
+        if let Ok(mut e) = self.emojis.write() {
+            *e = table;
         }
+        info!("Loaded {count} emoji aliases");
+        Ok(count)
+    }
 
+    /// Caches the names of the custom emojis uploaded to the Zulip realm
+    pub async fn cache_custom_emojis(&self) -> Result<()> {
+        let realm_emoji = self.zulip.get_realm_emoji().await?;
+        debug!("bot -> cache_custom_emojis -> GET realm emoji");
+        if let Ok(mut custom_emojis) = self.custom_emojis.write() {
+            *custom_emojis = realm_emoji.into_iter().map(|emoji| emoji.name).collect();
+        }
         Ok(())
     }
 
@@ -145,118 +243,686 @@ impl Bot {
     ///
     /// If the user did not send a valid bot command, it will reply with the help text
     ///
+    /// Direct messages are answered in the same conversation. Mentions (in a stream) are answered
+    /// privately to the sender so that the topic stays clean.
+    ///
     /// All responses should be valid Zulip Messsage Formatting
     pub async fn respond(&self, webhook: OutgoingWebhook) -> Reply {
-        if webhook.trigger == Trigger::Mention {
-            return Reply::ResponseNotRequired {
-                response_not_required: true,
-            };
-        }
-
+        let _span = logging::span("respond");
+        // The sender id is only trusted from Zulip, since it decides which commands may run
         if webhook.token != self.api_token {
-            info!(
+            warn!(
                 "Invalid bot token.\
                  Recieved an incoming webhook for a different bot?\
-                 bot_email={} bot_full_name={} token={}",
-                webhook.bot_email, webhook.bot_full_name, webhook.token,
-            )
+                 bot_email={} bot_full_name={} token={:?} sender_id={}",
+                webhook.bot_email, webhook.bot_full_name, webhook.token, webhook.message.sender_id,
+            );
+            return Reply::ResponseNotRequired {
+                response_not_required: true,
+            };
         }
 
-        let message = webhook.data;
-        let zulip_username = webhook.message.sender_full_name;
-        let command = self.parse_cmd(&message);
-
-        let entry = self.lookup_desk_id(&zulip_username);
-        if let Some(entry) = entry {
-            let (desk_id, desk_position) = entry;
-            return match self
-                .run_command(command, desk_id, &desk_position, &zulip_username)
-                .await
-            {
-                Ok(reply) => {
-                    if let Err(e) = self.send_bot_home().await {
-                        error!("Failed to send the bot home. Err = {e}");
+        let sender = Sender {
+            id: webhook.message.sender_id,
+            full_name: webhook.message.sender_full_name.clone(),
+        };
+        match webhook.trigger {
+            Trigger::DirectMessage => self.reply(&webhook.data, &sender).await,
+            Trigger::Mention => {
+                let not_required = Reply::ResponseNotRequired {
+                    response_not_required: true,
+                };
+                if let Some(stream) = webhook.message.stream_name() {
+                    if !self.is_stream_allowed(stream, webhook.message.stream_id) {
+                        debug!("bot -> respond -> mention in stream = {stream} which is not in the allowlist");
+                        return not_required;
                     }
-                    reply
                 }
-                Err(e) => Reply::Content {
-                    content: format!("Status Bot failed to perform your request :(\nPlease report this error to the Status Bot maintainer(s)\nError: {e}"),
-                },
-            };
+                let message = self.strip_mention(&webhook.data, &webhook.bot_full_name);
+                if let Reply::Content { content } = self.reply(&message, &sender).await {
+                    if let Err(e) = self.zulip.send_direct_message(&[sender.id], &content).await {
+                        error!(
+                            "Failed to send a private reply to sender_id = {}. Err = {e}",
+                            sender.id
+                        );
+                    }
+                }
+                not_required
+            }
+        }
+    }
+
+    /// Parses the message into a command, runs it, and builds the reply for the sender
+    async fn reply(&self, message: &str, sender: &Sender) -> Reply {
+        let mut span = logging::span("parse");
+        let parsed = self.parse_cmd(message);
+        if let Err(e) = &parsed {
+            span.fail(e);
+        }
+        drop(span);
+        let command = match parsed {
+            Ok(command) => command,
+            Err(e) => {
+                debug!("bot -> reply -> parse_cmd -> message = {message}, Err = {e:?}");
+                return Reply::Content {
+                    content: e.to_string(),
+                };
+            }
+        };
+        let result = self.run_command(command, sender).await;
+        self.stats.record_command(result.is_err());
+        match result {
+            Ok(reply) => reply,
             // TODO: Handle the result of the match. If any cmd methods returned a result, something
             // went wrong and we should reply to the user with a message saying "Status Bot was unable
             // to perform <cmd> because of <reason>. If you believe status bot is not working, please
             // write a Zulip message to <maintianers>"
+            Err(e) => Reply::Content {
+                content: format!("Status Bot failed to perform your request :(\nPlease report this error to the Status Bot maintainer(s)\nError: {e}"),
+            },
+        }
+    }
+
+    /// Checks the stream (by name or id) against the optional stream allowlist
+    fn is_stream_allowed(&self, stream_name: &str, stream_id: Option<u64>) -> bool {
+        match &self.stream_allowlist {
+            None => true,
+            Some(allowlist) => allowlist.iter().any(|allowed| {
+                allowed == stream_name || stream_id.is_some_and(|id| *allowed == id.to_string())
+            }),
         }
-        debug!("bot -> respond -> lookup_desk_id -> Unable to find a desk for this zulip_username = {zulip_username}. Replied with MISSING_DESK text");
-        return Reply::Content {
-            content: MISSING_DESK.into(),
-        };
     }
 
     /// Runs the function associated with the command
-    async fn run_command(
-        &self,
-        command: Command,
-        desk_id: usize,
-        desk_position: &Position,
-        zulip_username: &str,
-    ) -> Result<Reply> {
+    async fn run_command(&self, command: Command, sender: &Sender) -> Result<Reply> {
+        if let Err(required) = self.policy.authorize(sender.id, command.name()) {
+            warn!(
+                "audit -> denied -> sender_id = {}, sender = {}, role = {}, command = {command:?}, required role = {required}",
+                sender.id,
+                sender.full_name,
+                self.policy.role_of(sender.id)
+            );
+            return Ok(Reply::Content {
+                content: format!(
+                    "Sorry, `{}` can only be used by a Status Bot {required}",
+                    command.name()
+                ),
+            });
+        }
+        let zulip_username = sender.full_name.as_str();
         match command {
-            Command::Help => self.cmd_help().await,
-            Command::Show => self.cmd_show(desk_id).await,
+            Command::Help => self.cmd_help(sender).await,
+            Command::HelpFor(path) => self.cmd_help_for(sender, &path).await,
             // TODO: Enable clear when the RC API gets patched
             // Command::Clear => self.cmd_clear(desk_id, desk_position).await,
-            Command::Clear => self.cmd_help().await,
+            Command::Clear => self.cmd_help(sender).await,
             // Command::Feedback(feedback) => self.cmd_feedback(&feedback).await,
-            Command::Feedback(_) => self.cmd_help().await,
+            Command::Feedback(_) => self.cmd_help(sender).await,
             Command::SetName(rc_username) => self.cmd_set_name(zulip_username, rc_username).await,
-            Command::Status(status) => self.cmd_status(desk_id, desk_position, status).await,
             Command::ClearName => self.cmd_clear_name(zulip_username).await,
-            // Testing Commands (hidden)
-            Command::TestMissingDesk => self._cmd_test_missing_desk().await,
-            Command::TestLookupDesk(name) => self._cmd_test_lookup_desk(name).await,
-            Command::TestSendHome => self._cmd_test_send_home().await,
+            Command::Desk(desk_id) => self.cmd_desk(sender, desk_id).await,
+            Command::Emoji(query) => self.cmd_emoji(&query).await,
+            Command::Who(name) => self.cmd_who(&name).await,
+            Command::WhoIsAtDesk => self.cmd_who_is_at_desk().await,
+            Command::Team(group) => self.cmd_team(&group).await,
+            Command::TeamAdd(group, rc_username) => self.cmd_team_add(&group, rc_username).await,
+            Command::TeamRemove(group, rc_username) => {
+                self.cmd_team_remove(&group, &rc_username).await
+            }
+            Command::Pairs => self.cmd_pairs().await,
+            Command::History(n) => self.cmd_history(sender, n).await,
+            Command::Export(format) => self.cmd_export(sender, format).await,
+            Command::Delegate(delegate) => self.cmd_delegate(sender, delegate).await,
+            Command::Revoke(delegate) => self.cmd_revoke(sender, &delegate).await,
+            Command::StatusFor(owner, status, warnings) => {
+                self.cmd_status_for(sender, &owner, status, &warnings).await
+            }
+            Command::Preview(status, warnings) => self.cmd_preview(status, &warnings).await,
+            // Commands acting on the sender's own desk
+            Command::Show
+            | Command::Status(..)
+            | Command::Digest(_)
+            | Command::Pair(..)
+            | Command::Reuse(_) => self.run_desk_command(command, sender).await,
+            // Maintainer Commands (hidden)
+            Command::Admin(admin_command) => self.run_admin_command(admin_command).await,
+        }
+    }
+
+    /// Runs a maintainer command
+    async fn run_admin_command(&self, admin_command: AdminCommand) -> Result<Reply> {
+        const ADMIN_COMMAND: &str = "admin";
+        match admin_command {
+            AdminCommand::Help => {
+                let (_, admin) = find(ADMIN_COMMAND).expect("admin is a registered command");
+                Ok(Reply::Content {
+                    content: render_command_help(None, admin),
+                })
+            }
+            AdminCommand::Lookup(zulip_username) => self.cmd_admin_lookup(zulip_username).await,
+            AdminCommand::SetName(zulip_username, rc_username) => {
+                self.cmd_set_name(&zulip_username, rc_username).await
+            }
+            AdminCommand::ReleaseDesk(desk_id) => self.cmd_admin_release_desk(desk_id).await,
+            AdminCommand::RefreshDesks => self.cmd_admin_refresh_desks().await,
+            AdminCommand::SendHome => self.cmd_admin_send_home().await,
+            AdminCommand::Move(pos) => self.cmd_admin_move(pos).await,
+            AdminCommand::Stats => self.cmd_admin_stats().await,
+            AdminCommand::MissingDesk => self.cmd_admin_missing_desk().await,
+            AdminCommand::ReloadEmojis => self.cmd_admin_reload_emojis().await,
+            AdminCommand::EmojiFallbacks => self.cmd_admin_emoji_fallbacks().await,
+            AdminCommand::SetEmojiFallback(name, fallback) => {
+                self.cmd_admin_set_emoji_fallback(name, fallback).await
+            }
         }
     }
 
-    /// `status` - Sets the given status for the associated desk_id
+    /// Looks up the desk of the sender and runs the command against it.
+    ///
+    /// Updating a desk moves the bot next to it, so the bot is sent home afterwards
+    async fn run_desk_command(&self, command: Command, sender: &Sender) -> Result<Reply> {
+        let zulip_username = sender.full_name.as_str();
+        let (desk_id, desk_position) = match self.lookup_desk_id(sender.id, zulip_username).await {
+            DeskMatch::One(desk_id, desk_position) => (desk_id, desk_position),
+            DeskMatch::None => {
+                debug!("bot -> run_desk_command -> lookup_desk_id -> Unable to find a desk for this zulip_username = {zulip_username}. Replied with MISSING_DESK text");
+                return Ok(Reply::Content {
+                    content: MISSING_DESK.into(),
+                });
+            }
+            DeskMatch::Ambiguous(candidates) => {
+                return Ok(Reply::Content {
+                    content: desk_choice::render_ambiguous(&candidates),
+                })
+            }
+        };
+        let moves_bot = matches!(
+            command,
+            Command::Status(..) | Command::Pair(..) | Command::Reuse(_)
+        );
+        let result = match command {
+            Command::Show => self.cmd_show(desk_id).await,
+            Command::Status(status, warnings) => {
+                let reply = self
+                    .cmd_status(sender, desk_id, &desk_position, status, Source::Status)
+                    .await;
+                reply.map(|reply| with_warnings(reply, &warnings))
+            }
+            Command::Reuse(n) => self.cmd_reuse(sender, desk_id, &desk_position, n).await,
+            Command::Digest(enabled) => self.cmd_digest(desk_id, enabled).await,
+            Command::Pair(topic, duration) => {
+                self.cmd_pair(sender, desk_id, &desk_position, topic, duration)
+                    .await
+            }
+            _ => self.cmd_help(sender).await,
+        };
+        if moves_bot && result.is_ok() {
+            if let Err(e) = self.send_bot_home().await {
+                error!("Failed to send the bot home. Err = {e}");
+            }
+        }
+        result
+    }
+
+    /// `status` - Sets the given status for the associated desk_id and records it in the sender's
+    /// history
     async fn cmd_status(
         &self,
+        sender: &Sender,
         desk_id: usize,
         desk_position: &Position,
         status: Status,
+        source: Source,
     ) -> Result<Reply> {
+        let status = match self
+            .set_status(sender, desk_id, desk_position, status, source)
+            .await?
+        {
+            Ok(status) => status,
+            Err(reply) => return Ok(reply),
+        };
+        Ok(Reply::Content {
+            content: format!("**:check: Updated your status**: {status}"),
+        })
+    }
+
+    /// Validates and applies the status. Returns the reply to send instead when the status was not
+    /// set, because it is invalid or because of DRY_RUN
+    async fn set_status(
+        &self,
+        sender: &Sender,
+        desk_id: usize,
+        desk_position: &Position,
+        status: Status,
+        source: Source,
+    ) -> Result<std::result::Result<Status, Reply>> {
+        if self.dry_run {
+            return Ok(Err(self.cmd_preview(status, &[]).await?));
+        }
+        let errors = self.validate_status(&status);
+        if !errors.is_empty() {
+            return Ok(Err(invalid_status_reply(&errors)));
+        }
+        let status = self
+            .apply_status(sender, desk_id, desk_position, status, source)
+            .await?;
+        Ok(Ok(status))
+    }
+
+    /// `preview` - Shows the status as `status` would set it, without setting it
+    async fn cmd_preview(&self, status: Status, warnings: &[StatusWarning]) -> Result<Reply> {
+        let errors = self.validate_status(&status);
+        let (rc_status, custom_emoji) = self.rc_status(status.clone());
+        let emoji = match (&custom_emoji, &rc_status.emoji) {
+            (Some(custom), Some(fallback)) => {
+                format!("{custom} ({fallback} on your Virtual RC desk)")
+            }
+            _ => rc_status
+                .emoji
+                .as_deref()
+                .map_or("none".into(), |e| self.zulip_emoji(e)),
+        };
+        let expires_at = status.expires_at.map_or("never".into(), |dt| {
+            dt.format(&Iso8601::DEFAULT)
+                .map_or(String::new(), |ts| format!("<time:{ts}>"))
+        });
+        let mut content = format!(
+            "**:eyes: Preview of your status**, it was not set\n* Emoji: {emoji}\n* Text: {}\n* Expires: {expires_at}",
+            status.status.as_deref().unwrap_or("none"),
+        );
+        if !errors.is_empty() {
+            content.push_str(&format!("\n{}", invalid_status(&errors)));
+        }
+        Ok(with_warnings(Reply::Content { content }, warnings))
+    }
+
+    /// Checks the status against the limits of Zulip and of Virtual RC, before any request is made
+    fn validate_status(&self, status: &Status) -> Vec<ValidationError> {
+        let (rc_status, _) = self.rc_status(status.clone());
+        let mut errors = validation::validate(status, Target::Zulip);
+        errors.extend(validation::validate(&rc_status, Target::VirtualRc));
+        errors
+    }
+
+    /// The status as it is sent to Virtual RC, which only knows unicode emojis. Custom emojis are
+    /// replaced by their fallback, and returned
+    fn rc_status(&self, mut status: Status) -> (Status, Option<String>) {
+        let custom_emoji = status.emoji.take_if(|e| emoji::alias_name(e).is_some());
+        if let Some(custom_emoji) = &custom_emoji {
+            status.emoji = self.emoji_fallback(custom_emoji);
+        }
+        (status, custom_emoji)
+    }
+
+    /// Sets the given status for the desk of the owner and records it in the owner's history.
+    /// Returns the status of the desk as it was saved by Virtual RC
+    async fn apply_status(
+        &self,
+        owner: &Sender,
+        desk_id: usize,
+        desk_position: &Position,
+        status: Status,
+        source: Source,
+    ) -> Result<Status> {
+        let applied = status.clone();
+        let (status, custom_emoji) = self.rc_status(status);
+        if self.dry_run {
+            info!("dry run -> skipped update_desk -> desk_id = {desk_id}, status = {status:?}");
+            let Status {
+                emoji,
+                status,
+                expires_at,
+            } = status;
+            return Ok(self.zulip_status((emoji, status, expires_at), custom_emoji));
+        }
         match self.rc.update_desk(desk_id, desk_position, status).await {
             Ok(desk) => {
                 debug!("bot -> cmd_status -> update_desk -> SUCCES -> desk = {desk:#?}");
+                self.stats.record_status();
+                let entry = HistoryEntry {
+                    zulip_id: owner.id,
+                    status: applied,
+                    applied_at: OffsetDateTime::now_utc(),
+                    source,
+                };
+                if let Err(e) = self.history.append(entry) {
+                    error!("Failed to record the status in the history. Err = {e}");
+                }
                 let Desk {
                     emoji,
                     status,
                     expires_at,
                     ..
                 } = desk;
-                if let Some(e) = emoji.clone() {
-                    if let Some(zulip_alias) = self.emojis_inv.0.get(&e) {
-                        let status = Status::from((Some(zulip_alias.clone()), status, expires_at));
-                        return Ok(Reply::Content {
-                            content: format!("**:check: Updated your status**: {status}"),
-                        });
-                    }
-                }
-
-                let status = Status::from((emoji, status, expires_at));
-                Ok(Reply::Content {
-                    content: format!("**:check: Updated your status**: {status}"),
-                })
+                Ok(self.zulip_status((emoji, status, expires_at), custom_emoji))
             }
             Err(e) => {
-                debug!("bot -> cmd_status -> rc.update_desk -> returned error = {e}");
+                debug!("bot -> apply_status -> rc.update_desk -> returned error = {e}");
                 return Err(e);
             }
         }
     }
 
+    /// The status of a desk as it is shown in Zulip, with Zulip aliases instead of unicode emojis.
+    /// The custom emoji the status was set with replaces its fallback
+    fn zulip_status(&self, desk: StatusParts, custom_emoji: Option<String>) -> Status {
+        let (emoji, status, expires_at) = desk;
+        if emoji.is_some() && custom_emoji.is_some() {
+            return Status::from((custom_emoji, status, expires_at));
+        }
+        if let Some(e) = emoji.clone() {
+            if let Some(zulip_alias) = self.read_emojis(|t| t.zulip_alias(&e).cloned()) {
+                return Status::from((Some(zulip_alias), status, expires_at));
+            }
+        }
+        Status::from((emoji, status, expires_at))
+    }
+
+    /// `status for` - Sets the status of someone who delegated their desk to the sender, and lets
+    /// them know about it
+    async fn cmd_status_for(
+        &self,
+        sender: &Sender,
+        owner: &Mention,
+        status: Status,
+        warnings: &[StatusWarning],
+    ) -> Result<Reply> {
+        let owner_id = match self.resolve_mention(owner.clone()).await? {
+            Ok(owner) => owner.id.unwrap_or_default(),
+            Err(content) => return Ok(Reply::Content { content }),
+        };
+        let delegation = self
+            .delegations
+            .read(|d| d.find(owner_id, sender.id).cloned());
+        let Some(Delegation {
+            owner_id,
+            owner_name,
+            ..
+        }) = delegation
+        else {
+            return Ok(Reply::Content {
+                content: format!(
+                    "**{}** has not delegated their desk to you. They can do so with `delegate @**{}**`",
+                    owner.name, sender.full_name
+                ),
+            });
+        };
+        let (desk_id, desk_position) = match self.lookup_desk_id(owner_id, &owner_name).await {
+            DeskMatch::One(desk_id, desk_position) => (desk_id, desk_position),
+            DeskMatch::None => {
+                return Ok(Reply::Content {
+                    content: format!("Could not find a Virtual RC desk for **{owner_name}**"),
+                })
+            }
+            DeskMatch::Ambiguous(_) => {
+                return Ok(Reply::Content {
+                    content: format!("**{owner_name}** owns several Virtual RC desks and has not chosen theirs with `desk` yet"),
+                })
+            }
+        };
+        if self.dry_run {
+            return self.cmd_preview(status, warnings).await;
+        }
+        let errors = self.validate_status(&status);
+        if !errors.is_empty() {
+            return Ok(invalid_status_reply(&errors));
+        }
+        let owner = Sender {
+            id: owner_id,
+            full_name: owner_name,
+        };
+        let status = self
+            .apply_status(&owner, desk_id, &desk_position, status, Source::Delegate)
+            .await?;
+        if let Err(e) = self.send_bot_home().await {
+            error!("Failed to send the bot home. Err = {e}");
+        }
+        let notification = format!(
+            "**{}** updated your status: {status}\nYou can stop them with `revoke @**{}**`",
+            sender.full_name, sender.full_name
+        );
+        if let Err(e) = self
+            .zulip
+            .send_direct_message(&[owner.id], &notification)
+            .await
+        {
+            error!(
+                "Failed to notify owner_id = {} of their new status. Err = {e}",
+                owner.id
+            );
+        }
+        let reply = Reply::Content {
+            content: format!(
+                "**:check: Updated the status of {}**: {status}",
+                owner.full_name
+            ),
+        };
+        Ok(with_warnings(reply, warnings))
+    }
+
+    /// `delegate` - Lets another Zulip user set the sender's status with `status for`
+    async fn cmd_delegate(&self, sender: &Sender, delegate: Mention) -> Result<Reply> {
+        let delegate = match self.resolve_mention(delegate).await? {
+            Ok(delegate) => delegate,
+            Err(content) => return Ok(Reply::Content { content }),
+        };
+        if delegate.is(sender.id) {
+            return Ok(Reply::Content {
+                content: "You can already set your own status".into(),
+            });
+        }
+        let name = delegate.name.clone();
+        let granted = self.delegations.update(|d| {
+            d.grant(Delegation {
+                owner_id: sender.id,
+                owner_name: sender.full_name.clone(),
+                delegate,
+            })
+        })?;
+        let content = match granted {
+            true => format!(
+                "**{name}** can now set your status with `status for @**{}** ...`. You will get a message whenever they do",
+                sender.full_name
+            ),
+            false => format!("**{name}** can already set your status"),
+        };
+        Ok(Reply::Content { content })
+    }
+
+    /// `revoke` - Stops another Zulip user from setting the sender's status
+    async fn cmd_revoke(&self, sender: &Sender, delegate: &Mention) -> Result<Reply> {
+        let delegate = match self.resolve_mention(delegate.clone()).await? {
+            Ok(delegate) => delegate,
+            Err(content) => return Ok(Reply::Content { content }),
+        };
+        let delegate_id = delegate.id.unwrap_or_default();
+        let revoked = self
+            .delegations
+            .update(|d| d.revoke(sender.id, delegate_id))?;
+        let content = match revoked {
+            true => format!("**{}** can no longer set your status", delegate.name),
+            false => format!("**{}** could not set your status", delegate.name),
+        };
+        Ok(Reply::Content { content })
+    }
+
+    /// Finds the Zulip user id of a mention without one. The error is the reply to send when the name
+    /// does not belong to exactly one Zulip user
+    async fn resolve_mention(
+        &self,
+        mention: Mention,
+    ) -> Result<std::result::Result<Mention, String>> {
+        if mention.id.is_some() {
+            return Ok(Ok(mention));
+        }
+        let members = self.zulip.get_users().await?;
+        Ok(mention.resolve(&members))
+    }
+
+    /// `pair` - Sets a :pear: status and lights a pairing beacon for the sender. When someone else
+    /// is looking to pair on a similar topic both people get a direct message
+    async fn cmd_pair(
+        &self,
+        sender: &Sender,
+        desk_id: usize,
+        desk_position: &Position,
+        topic: String,
+        duration: Option<Duration>,
+    ) -> Result<Reply> {
+        let now = OffsetDateTime::now_utc();
+        let expires_at = now + duration.unwrap_or(Self::DEFAULT_PAIR_DURATION);
+        let status = Status {
+            emoji: Some(emojic::flat::PEAR.grapheme.into()),
+            status: Some(format!("Looking to pair on {topic}")),
+            expires_at: Some(expires_at),
+        };
+        // Nobody is told about a beacon unless the :pear: status was really set
+        let status = match self
+            .set_status(sender, desk_id, desk_position, status, Source::Pair)
+            .await?
+        {
+            Ok(status) => status,
+            Err(reply) => return Ok(reply),
+        };
+
+        let beacon = Beacon {
+            zulip_id: sender.id,
+            name: sender.full_name.clone(),
+            desk_id,
+            topic,
+            expires_at,
+        };
+        let matches: Vec<Beacon> = self.beacons.update(|beacons| {
+            beacons.light(beacon.clone(), now);
+            beacons.matches(&beacon, now).into_iter().cloned().collect()
+        })?;
+        for other in &matches {
+            let content = format!(
+                ":pear: **Pairing match!** @**{}** wants to pair on *{}* and @**{}** wants to pair on *{}*. Have fun!",
+                beacon.name, beacon.topic, other.name, other.topic
+            );
+            if let Err(e) = self
+                .zulip
+                .send_direct_message(&[beacon.zulip_id, other.zulip_id], &content)
+                .await
+            {
+                error!("Failed to notify a pairing match. Err = {e}");
+            }
+        }
+
+        let content = format!("**:check: Updated your status**: {status}");
+        let content = match matches.len() {
+            0 => format!("{content}\nNobody else is looking to pair on this yet. Status Bot will message you when someone is!"),
+            n => format!("{content}\nFound {n} pairing match(es), check your direct messages!"),
+        };
+        Ok(Reply::Content { content })
+    }
+
+    /// `pairs` - Lists everyone currently looking to pair, from beacons and :pear: desk statuses
+    async fn cmd_pairs(&self) -> Result<Reply> {
+        let now = OffsetDateTime::now_utc();
+        let format_expires_at = |dt: OffsetDateTime| {
+            dt.format(&Iso8601::DEFAULT)
+                .map_or(String::new(), |ts| format!("<time:{ts}>"))
+        };
+        let mut rows: Vec<[String; 3]> = self.beacons.read(|beacons| {
+            beacons
+                .active(now)
+                .into_iter()
+                .map(|b| {
+                    [
+                        b.name.clone(),
+                        b.topic.clone(),
+                        format_expires_at(b.expires_at),
+                    ]
+                })
+                .collect()
+        });
+        let beacon_desks: Vec<usize> = self
+            .beacons
+            .read(|b| b.active(now).iter().map(|b| b.desk_id).collect());
+        let pear = emojic::flat::PEAR.grapheme;
+        self.read_desks(|desks| {
+            for desk in desks {
+                let status = desk.status.clone().unwrap_or_default();
+                let is_pairing =
+                    desk.emoji.as_deref() == Some(pear) || status.to_lowercase().contains("pair");
+                if is_pairing && has_active_status(desk, now) && !beacon_desks.contains(&desk.id) {
+                    let name = desk.owner.as_ref().map_or("", |o| o.name.as_str()).into();
+                    let expires_at = desk.expires_at.map_or(String::new(), format_expires_at);
+                    rows.push([name, status, expires_at]);
+                }
+            }
+        });
+        let content = match rows.len() {
+            0 => "Nobody is looking to pair right now. Start with `pair {topic}`!".into(),
+            _ => render_table(&["Name", "Pairing on", "Until"], &rows),
+        };
+        Ok(Reply::Content { content })
+    }
+
+    /// `history` - Lists the sender's `n` most recent statuses
+    async fn cmd_history(&self, sender: &Sender, n: usize) -> Result<Reply> {
+        let entries = self.history.recent(sender.id, n);
+        if entries.is_empty() {
+            return Ok(Reply::Content {
+                content: "You have not set any statuses with Status Bot yet".into(),
+            });
+        }
+        let lines: Vec<String> = entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| {
+                let status = Status {
+                    emoji: entry.status.emoji.as_ref().map(|e| self.zulip_emoji(e)),
+                    status: entry.status.status.clone(),
+                    expires_at: None,
+                };
+                let applied_at = entry
+                    .applied_at
+                    .format(&Iso8601::DEFAULT)
+                    .map_or(String::new(), |ts| format!("<time:{ts}>"));
+                format!(
+                    "{}. {status} (`{}`, {applied_at})",
+                    i + 1,
+                    entry.source.as_str()
+                )
+            })
+            .collect();
+        Ok(Reply::Content {
+            content: format!(
+                "**Your recent statuses**\n{}\n\nSet one of them again with `reuse {{n}}`",
+                lines.join("\n")
+            ),
+        })
+    }
+
+    /// `reuse` - Applies the emoji and text of the `n`th most recent status again
+    async fn cmd_reuse(
+        &self,
+        sender: &Sender,
+        desk_id: usize,
+        desk_position: &Position,
+        n: usize,
+    ) -> Result<Reply> {
+        let Some(entry) = self.history.recent(sender.id, n).into_iter().nth(n - 1) else {
+            return Ok(Reply::Content {
+                content: format!("There is no status #{n} in your history. See `history`"),
+            });
+        };
+        // The old expiration most likely passed already, so the status gets a fresh default one
+        let status = Status::from((entry.status.emoji, entry.status.status, None));
+        self.cmd_status(sender, desk_id, desk_position, status, Source::Reuse)
+            .await
+    }
+
+    /// `export` - Replies with the sender's whole history as JSON or CSV
+    async fn cmd_export(&self, sender: &Sender, format: ExportFormat) -> Result<Reply> {
+        let entries = self.history.for_user(sender.id);
+        let content = history::export(&entries, format, MAX_MESSAGE_LENGTH)?;
+        Ok(Reply::Content { content })
+    }
+
     /// `show` - Displays the user's current status on Virtual RC
     async fn cmd_show(&self, desk_id: usize) -> Result<Reply> {
         match self.rc.get_desk(desk_id).await {
@@ -271,8 +937,8 @@ impl Bot {
                 // because Zulip does not seem to be able to render the raw unicode when the
                 // server is running in Docker Debian container
                 if let Some(e) = emoji {
-                    if let Some(zulip_alias) = self.emojis_inv.0.get(&e) {
-                        s = Status::from((Some(zulip_alias.clone()), status, expires_at));
+                    if let Some(zulip_alias) = self.read_emojis(|t| t.zulip_alias(&e).cloned()) {
+                        s = Status::from((Some(zulip_alias), status, expires_at));
                     }
                 }
                 let status_str = format!("{s}");
@@ -298,108 +964,620 @@ impl Bot {
         todo!()
     }
 
-    /// `help` - Responds to the user with a help message detailing the different comands and configurations
-    /// they can run using StatusBot
-    async fn cmd_help(&self) -> Result<Reply> {
+    /// `help` - Responds to the user with a help message detailing the different comands and configurations
+    /// they can run using StatusBot
+    ///
+    /// Only the commands the sender is allowed to run are listed
+    async fn cmd_help(&self, sender: &Sender) -> Result<Reply> {
+        let content = render_help(|spec| self.policy.authorize(sender.id, spec.name).is_ok());
+        Ok(Reply::Content { content })
+    }
+
+    /// `help {command}` - Explains a single command. Commands the sender is not allowed to run
+    /// are treated as unknown
+    async fn cmd_help_for(&self, sender: &Sender, path: &str) -> Result<Reply> {
+        let visible = |spec: &CommandSpec| self.policy.authorize(sender.id, spec.name).is_ok();
+        let content = match find(path) {
+            Some((None, spec)) if visible(spec) => render_command_help(None, spec),
+            Some((Some(parent), spec)) if visible(parent) => {
+                render_command_help(Some(parent), spec)
+            }
+            _ => ParseError::Unknown {
+                command: path.into(),
+                suggestion: None,
+            }
+            .to_string(),
+        };
+        Ok(Reply::Content { content })
+    }
+
+    /// `feedback` - Writes feedback to the bot authors
+    async fn cmd_feedback(&self, _feedback: &str) -> Result<Reply> {
+        // TODO: Send a Zulip message with the feedback to the list of maintainers configured in .env
+        todo!()
+    }
+
+    /// `set_name` - Updates the Zulip user's display name. Used resolving naming issues between
+    /// Zulip and Virtual RC. This adds an entry in self.corrected_names
+    ///
+    /// Names are looked up after removing the pronouns and batch, so they are stored that way too
+    async fn cmd_set_name(&self, zulip_username: &str, rc_username: String) -> Result<Reply> {
+        let zulip_username = self.parse_zulip_username(zulip_username);
+        self.corrected_names.rcu(|corrected_names| {
+            let mut corrected_names = HashMap::clone(corrected_names);
+            corrected_names.insert(zulip_username.clone(), rc_username.clone());
+            corrected_names
+        });
+        Ok(Reply::Content {
+            content: format!(
+                "Set the Virtual RC username of '{zulip_username}' to '{rc_username}'"
+            ),
+        })
+    }
+
+    async fn cmd_clear_name(&self, zulip_username: &str) -> Result<Reply> {
+        let zulip_username = self.parse_zulip_username(zulip_username);
+        let previous = self.corrected_names.rcu(|corrected_names| {
+            let mut corrected_names = HashMap::clone(corrected_names);
+            corrected_names.remove(&zulip_username);
+            corrected_names
+        });
+        match previous.get(&zulip_username) {
+            Some(rc_username) => Ok(Reply::Content { content: format!("Removed your corrected Virtual RC username '{rc_username}'") }),
+            None => Ok(Reply::Content { content: "There was not a corrected Virtual RC username associated with your Zulip account. Did you `set_name` already?".into() })
+        }
+    }
+
+    /// `desk` - Remembers which of the desks owned by the sender's name is theirs
+    async fn cmd_desk(&self, sender: &Sender, desk_id: usize) -> Result<Reply> {
+        let candidates = self.desk_candidates(&sender.full_name);
+        let Some((_, pos)) = candidates.iter().find(|(id, _)| *id == desk_id) else {
+            let content = match candidates.as_slice() {
+                [] => MISSING_DESK.into(),
+                candidates => format!(
+                    "Desk `{desk_id}` is not owned by your Virtual RC name\n\n{}",
+                    desk_choice::render_ambiguous(candidates)
+                ),
+            };
+            return Ok(Reply::Content { content });
+        };
+        // A desk is only chosen between several, so a namesake cannot take the only desk of a name
+        if candidates.len() == 1 {
+            return Ok(Reply::Content {
+                content: format!("Desk `{desk_id}` is the only desk owned by your Virtual RC name, Status Bot already uses it"),
+            });
+        }
+        let claimed = self
+            .desk_choices
+            .update(|choices| choices.claim(sender.id, desk_id))?;
+        if claimed.is_err() {
+            return Ok(Reply::Content {
+                content: format!("Desk `{desk_id}` was already chosen by someone else with your Virtual RC name. If it is yours, ask one of the Status Bot maintainers to release it with `admin release_desk {desk_id}`"),
+            });
+        }
+        Ok(Reply::Content {
+            content: format!(
+                "Status Bot will use desk `{desk_id}` at ({}, {}) as yours",
+                pos.x, pos.y
+            ),
+        })
+    }
+
+    /// `emoji` - Lists the emojis whose alias or name is closest to the query
+    async fn cmd_emoji(&self, query: &str) -> Result<Reply> {
+        let found = self.read_emojis(|t| t.search(query, Self::EMOJI_SEARCH_RESULTS));
+        if found.is_empty() {
+            return Ok(Reply::Content {
+                content: format!("No emoji matches `{query}`"),
+            });
+        }
+        let lines: Vec<String> = found
+            .into_iter()
+            .map(|found| match found.alias {
+                Some(alias) => format!("* {alias} `{alias}`"),
+                // Without a Zulip alias the emoji can only be sent as is
+                None => format!("* {} {}", found.unicode, found.name.replace('_', " ")),
+            })
+            .collect();
+        Ok(Reply::Content {
+            content: format!("Emojis matching `{query}`:\n{}", lines.join("\n")),
+        })
+    }
+
+    /// `who` - Shows the status of every desk owner whose Virtual RC name contains `name`
+    async fn cmd_who(&self, name: &str) -> Result<Reply> {
+        let name = name.to_lowercase();
+        let content = self.read_desks(|desks| {
+            let matches: Vec<&Desk> = desks
+                .iter()
+                .filter(|desk| {
+                    desk.owner
+                        .as_ref()
+                        .is_some_and(|owner| owner.name.to_lowercase().contains(&name))
+                })
+                .collect();
+            match matches.len() {
+                0 => format!("No Virtual RC desk owner matches '{name}'"),
+                _ => self.render_desks(&matches),
+            }
+        });
+        Ok(Reply::Content { content })
+    }
+
+    /// `who is at their desk` - Shows everyone who currently has an unexpired status
+    async fn cmd_who_is_at_desk(&self) -> Result<Reply> {
+        let now = OffsetDateTime::now_utc();
+        let content = self.read_desks(|desks| {
+            let active: Vec<&Desk> = desks
+                .iter()
+                .filter(|desk| has_active_status(desk, now))
+                .collect();
+            match active.len() {
+                0 => "Nobody has set a status right now".into(),
+                _ => self.render_desks(&active),
+            }
+        });
+        Ok(Reply::Content { content })
+    }
+
+    /// `team` - Shows the status of every member of the group. Lists the known groups when no
+    /// group is given
+    async fn cmd_team(&self, group: &str) -> Result<Reply> {
+        if group.is_empty() {
+            let content = self.groups.read(|groups| match groups.0.len() {
+                0 => "There are no teams yet. Create one with `team_add {group} {name}`".into(),
+                _ => groups
+                    .0
+                    .iter()
+                    .map(|(group, members)| format!("* `{group}` ({} members)", members.len()))
+                    .collect::<Vec<String>>()
+                    .join("\n"),
+            });
+            return Ok(Reply::Content { content });
+        }
+
+        let Some(members) = self.groups.read(|groups| groups.members(group)) else {
+            return Ok(Reply::Content {
+                content: format!("There is no team called `{group}`"),
+            });
+        };
+        let content = self.read_desks(|desks| {
+            let member_desks: Vec<&Desk> = desks
+                .iter()
+                .filter(|desk| {
+                    desk.owner.as_ref().is_some_and(|owner| {
+                        members.iter().any(|m| m.eq_ignore_ascii_case(&owner.name))
+                    })
+                })
+                .collect();
+            let missing: Vec<&String> = members
+                .iter()
+                .filter(|m| {
+                    !member_desks.iter().any(|desk| {
+                        desk.owner
+                            .as_ref()
+                            .is_some_and(|owner| m.eq_ignore_ascii_case(&owner.name))
+                    })
+                })
+                .collect();
+            let mut content = format!("**Team `{group}`**\n{}", self.render_desks(&member_desks));
+            if !missing.is_empty() {
+                let missing: Vec<&str> = missing.iter().map(|m| m.as_str()).collect();
+                content.push_str(&format!(
+                    "\nNo Virtual RC desk found for: {}",
+                    missing.join(", ")
+                ));
+            }
+            content
+        });
+        Ok(Reply::Content { content })
+    }
+
+    /// `team_add` - Adds a Virtual RC username to a group, creating the group if needed
+    async fn cmd_team_add(&self, group: &str, rc_username: String) -> Result<Reply> {
+        let content = format!("Added '{rc_username}' to team `{group}`");
+        self.groups
+            .update(|groups| groups.add(group, rc_username))?;
+        Ok(Reply::Content { content })
+    }
+
+    /// `team_remove` - Removes a Virtual RC username from a group
+    async fn cmd_team_remove(&self, group: &str, rc_username: &str) -> Result<Reply> {
+        let removed = self
+            .groups
+            .update(|groups| groups.remove(group, rc_username))?;
+        Ok(Reply::Content {
+            content: match removed {
+                true => format!("Removed '{rc_username}' from team `{group}`"),
+                false => format!("'{rc_username}' is not a member of team `{group}`"),
+            },
+        })
+    }
+
+    /// `digest` - Opts the sender's desk in to (or out of) the daily digest
+    async fn cmd_digest(&self, desk_id: usize, enabled: bool) -> Result<Reply> {
+        self.digest_opt_ins.update(|opt_ins| match enabled {
+            true => opt_ins.0.insert(desk_id),
+            false => opt_ins.0.remove(&desk_id),
+        })?;
+        let content = match (enabled, &self.digest) {
+            (false, _) => "You will no longer appear in the daily digest".into(),
+            (true, Some(digest)) => format!(
+                "Your status will appear in the daily digest posted to #**{}>{}**",
+                digest.stream, digest.topic
+            ),
+            (true, None) => {
+                "You opted in to the daily digest, but the digest is not enabled for Status Bot yet"
+                    .into()
+            }
+        };
+        Ok(Reply::Content { content })
+    }
+
+    /// The time of day (UTC) the daily digest should be posted. None when the digest is disabled
+    pub fn digest_time(&self) -> Option<Time> {
+        self.digest.as_ref().map(|digest| digest.at)
+    }
+
+    /// Posts the statuses of everyone who opted in to the daily digest to the configured stream
+    pub async fn post_digest(&self) -> Result<()> {
+        let Some(digest) = &self.digest else {
+            return Err("The daily digest is not enabled".into());
+        };
+        let now = OffsetDateTime::now_utc();
+        let opt_ins = self.digest_opt_ins.read(|opt_ins| opt_ins.0.clone());
+        let content = self.read_desks(|desks| {
+            let included: Vec<&Desk> = desks
+                .iter()
+                .filter(|desk| opt_ins.contains(&desk.id))
+                .filter(|desk| has_active_status(desk, now))
+                .filter(|desk| digest.matches(desk.status.as_deref().unwrap_or_default()))
+                .collect();
+            match included.len() {
+                0 => None,
+                _ => Some(format!(
+                    "**Today's statuses**\n{}\n\nWant to show up here? Send `digest on` to Status Bot",
+                    self.render_desks(&included)
+                )),
+            }
+        });
+        match content {
+            Some(content) if self.dry_run => {
+                info!("dry run -> skipped posting the digest -> content = {content}")
+            }
+            Some(content) => {
+                self.zulip
+                    .send_stream_message(&digest.stream, &digest.topic, &content)
+                    .await?;
+            }
+            None => info!("bot -> post_digest -> nobody to include in today's digest"),
+        }
+        Ok(())
+    }
+
+    /// `admin missing_desk` - Replies with the MISSING_DESK help text
+    async fn cmd_admin_missing_desk(&self) -> Result<Reply> {
+        debug!(
+            "AdminCommand::MissingDesk -> MISSING_DESK = {}",
+            MISSING_DESK
+        );
+        Ok(Reply::Content {
+            content: MISSING_DESK.into(),
+        })
+    }
+
+    /// `admin release_desk` - Forgets who chose the desk, so that its owner can choose it again
+    async fn cmd_admin_release_desk(&self, desk_id: usize) -> Result<Reply> {
+        let released = self
+            .desk_choices
+            .update(|choices| choices.release(desk_id))?;
+        let content = match released {
+            Some(zulip_id) => {
+                info!(
+                    "audit -> release_desk -> desk_id = {desk_id}, chosen by zulip_id = {zulip_id}"
+                );
+                format!("Released desk `{desk_id}`, it was chosen by Zulip user `{zulip_id}`. Its owner can now choose it with `desk {desk_id}`")
+            }
+            None => format!("Nobody chose desk `{desk_id}`"),
+        };
+        Ok(Reply::Content { content })
+    }
+
+    /// `admin lookup` - Explains how a Zulip username is matched to a Virtual RC desk
+    async fn cmd_admin_lookup(&self, zulip_username: String) -> Result<Reply> {
+        debug!("AdminCommand::Lookup -> zulip_username = {zulip_username}");
+        let lookup = self.lookup(&zulip_username);
+        Ok(Reply::Content {
+            content: format!(
+                "**Looking up the Virtual RC desk:**\n* Parsed Zulup username: `{:?}`\n* Virtual RC username match: `{:?}`\n* Virtual RC desk ids: `{:?}`",
+                lookup.parsed,
+                lookup.name,
+                lookup.desks.iter().map(|(id, _pos)| *id).collect::<Vec<_>>()
+            ),
+        })
+    }
+
+    /// Each step of matching a Zulip username to a desk, see [`Bot::lookup_desk_id`]
+    pub fn lookup(&self, zulip_username: &str) -> DeskLookup {
+        let parsed = self.parse_zulip_username(zulip_username);
+        let name = self.lookup_corrected_name(&parsed);
+        let desks = self
+            .desk_owners
+            .load()
+            .get(&name)
+            .cloned()
+            .unwrap_or_default();
+        DeskLookup {
+            parsed,
+            name,
+            desks,
+        }
+    }
+
+    /// Every cached desk owner with their desk id and position, sorted by name. Names owning several
+    /// desks are listed once per desk
+    pub fn owned_desks(&self) -> Vec<(String, usize, Position)> {
+        let mut desks: Vec<(String, usize, Position)> = self
+            .desk_owners
+            .load()
+            .iter()
+            .flat_map(|(name, desks)| {
+                desks
+                    .iter()
+                    .map(|(desk_id, pos)| (name.clone(), *desk_id, pos.clone()))
+            })
+            .collect();
+        desks.sort_by(|a, b| (&a.0, a.1).cmp(&(&b.0, b.1)));
+        desks
+    }
+
+    /// Sets the status of any desk from the command line (`statusbot set-status`). The status is
+    /// written like the argument of `status`. No one sent it, so it is not recorded in a history
+    pub async fn set_desk_status(&self, desk_id: usize, input: String) -> Result<String> {
+        let (status, warnings) = self.parse_status(input);
+        let mut output: Vec<String> = warnings.iter().map(|w| format!("warning: {w}")).collect();
+        let errors = self.validate_status(&status);
+        if !errors.is_empty() {
+            let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+            return Err(format!("Cannot set this status:\n{}", errors.join("\n")).into());
+        }
+        let desk = self.rc.get_desk(desk_id).await?;
+        let (status, _) = self.rc_status(status);
+        if self.dry_run {
+            output.push(format!(
+                "DRY_RUN is set, desk {desk_id} was not set to: {status}"
+            ));
+            return Ok(output.join("\n"));
+        }
+        let updated = self.rc.update_desk(desk_id, &desk.pos, status).await;
+        // Whether or not the desk was updated, the bot may have moved next to it
+        if let Err(e) = self.send_bot_home().await {
+            output.push(format!("warning: failed to send the bot home: {e}"));
+        }
+        let Desk {
+            emoji,
+            status,
+            expires_at,
+            ..
+        } = updated?;
+        let status = Status {
+            emoji,
+            status,
+            expires_at,
+        };
+        output.push(format!("Updated desk {desk_id}: {status}"));
+        Ok(output.join("\n"))
+    }
+
+    /// `admin refresh_desks` - Refreshes the desk cache right away
+    async fn cmd_admin_refresh_desks(&self) -> Result<Reply> {
+        self.cache_desk_owners().await?;
+        let count = self.desks.load().len();
+        Ok(Reply::Content {
+            content: format!("Refreshed the desk cache, found {count} owned desks"),
+        })
+    }
+
+    /// `admin send_home` - Sends Status Bot to its home location
+    async fn cmd_admin_send_home(&self) -> Result<Reply> {
+        match self.send_bot_home().await {
+            Ok(_) => Ok(Reply::Content {
+                content: "Sent bot home".into(),
+            }),
+            Err(e) => Ok(Reply::Content {
+                content: format!("Failed to to send bot home: {e}"),
+            }),
+        }
+    }
+
+    /// `admin move` - Sends Status Bot to any location
+    async fn cmd_admin_move(&self, pos: Position) -> Result<Reply> {
+        let req = UpdateBotRequest {
+            x: Some(pos.x),
+            y: Some(pos.y),
+            ..Default::default()
+        };
+        if self.dry_run {
+            return Ok(Reply::Content {
+                content: format!("Dry run, did not move bot to ({}, {})", pos.x, pos.y),
+            });
+        }
+        match self.rc.update_bot(req).await {
+            Ok(_) => Ok(Reply::Content {
+                content: format!("Moved bot to ({}, {})", pos.x, pos.y),
+            }),
+            Err(e) => Ok(Reply::Content {
+                content: format!("Failed to move bot to ({}, {}): {e}", pos.x, pos.y),
+            }),
+        }
+    }
+
+    /// `admin stats` - Shows what Status Bot has been doing since it started
+    async fn cmd_admin_stats(&self) -> Result<Reply> {
+        let desks = self.desks.load().len();
+        let corrected_names = self.corrected_names.load().len();
+        let last_desk_refresh = self.stats.last_desk_refresh().map_or("never".into(), |dt| {
+            dt.format(&Iso8601::DEFAULT)
+                .map_or(String::new(), |ts| format!("<time:{ts}>"))
+        });
+        let uptime = self.stats.uptime();
+        let rows = [
+            [
+                "Uptime".into(),
+                format!("{}h {}m", uptime / 3600, uptime % 3600 / 60),
+            ],
+            ["Commands".into(), self.stats.commands().to_string()],
+            ["Errors".into(), self.stats.errors().to_string()],
+            ["Statuses set".into(), self.stats.statuses().to_string()],
+            ["Cached desks".into(), desks.to_string()],
+            ["Corrected names".into(), corrected_names.to_string()],
+            ["Last desk refresh".into(), last_desk_refresh],
+        ];
         Ok(Reply::Content {
-            content: HELP_TEXT.into(),
+            content: render_table(&["Stat", "Value"], &rows),
         })
     }
 
-    /// `feedback` - Writes feedback to the bot authors
-    async fn cmd_feedback(&self, _feedback: &str) -> Result<Reply> {
-        // TODO: Send a Zulip message with the feedback to the list of maintainers configured in .env
-        todo!()
-    }
-
-    /// `set_name` - Updates the Zulip user's display name. Used resolving naming issues between
-    /// Zulip and Virtual RC. This adds an entry in self.corrected_names
-    async fn cmd_set_name(&self, zulip_username: &str, rc_username: String) -> Result<Reply> {
-        match self.corrected_names.try_write() {
-            Ok(mut corrected_names) => {
-                corrected_names
-                    .entry(zulip_username.into())
-                    .and_modify(|rc| *rc = rc_username.clone())
-                    .or_insert_with(|| rc_username.clone());
-                Ok(Reply::Content {
-                    content: format!("Set your Virtual RC username to '{rc_username}'"),
-                })
+    /// `admin reload_emojis` - Loads the emoji table again
+    async fn cmd_admin_reload_emojis(&self) -> Result<Reply> {
+        let source = self
+            .emoji_source
+            .as_deref()
+            .unwrap_or("the bundled zulip.json");
+        let mut content = match self.reload_emojis().await {
+            Ok(count) => format!("Loaded {count} emoji aliases from {source}"),
+            Err(e) => format!("Failed to load the emoji table from {source}: {e}"),
+        };
+        match self.cache_custom_emojis().await {
+            Ok(()) => {
+                let count = self.custom_emojis.read().map_or(0, |c| c.len());
+                content.push_str(&format!("\nLoaded {count} custom emojis from Zulip"));
             }
-            Err(_) => Ok(Reply::Content { content: "Failed to set your corrected username. So sorry! Please try again. If this persists, then it is a bug. Please write a message to one of the StatusBot maintainers".into() })
+            Err(e) => content.push_str(&format!(
+                "\nFailed to load the custom emojis from Zulip: {e}"
+            )),
         }
+        Ok(Reply::Content { content })
     }
 
-    async fn cmd_clear_name(&self, zulip_username: &str) -> Result<Reply> {
-        match self.corrected_names.try_write() {
-            Ok(mut corrected_names)  => {
-                match corrected_names.remove(zulip_username.into()) {
-                    Some(rc_username) => Ok(Reply::Content { content: format!("Removed your corrected Virtual RC username '{rc_username}'") }),
-                    None => Ok(Reply::Content { content: "There was not a corrected Virtual RC username associated with your Zulip account. Did you `set_name` already?".into() })
-                }
-        },
-            Err(_) => Ok(Reply::Content { content: "Failed to set your corrected username. So sorry! Please try again. If this persists, then it is a bug. Please write a message to one of the StatusBot maintainers".into() })
+    /// `admin emoji_fallback` - Lists the custom emojis of the realm and their fallbacks
+    async fn cmd_admin_emoji_fallbacks(&self) -> Result<Reply> {
+        if let Err(e) = self.cache_custom_emojis().await {
+            error!("Failed to fetch the realm emoji. Err = {e}");
         }
-    }
-
-    /// Testing function to return MISSING_DESK help text
-    async fn _cmd_test_missing_desk(&self) -> Result<Reply> {
-        debug!(
-            "Command::TestMissingDesk -> MISSING_DESK = {}",
-            MISSING_DESK
-        );
+        let custom_emojis = self
+            .custom_emojis
+            .read()
+            .map_or(BTreeSet::new(), |c| c.clone());
+        let fallbacks = self.emoji_fallbacks.read(|f| f.0.clone());
+        // Fallbacks of custom emojis that were removed from the realm are listed too
+        let names: BTreeSet<&String> = custom_emojis.iter().chain(fallbacks.keys()).collect();
+        if names.is_empty() {
+            return Ok(Reply::Content {
+                content: "This Zulip realm has no custom emojis".into(),
+            });
+        }
+        let rows: Vec<[String; 2]> = names
+            .into_iter()
+            .map(|name| {
+                let fallback = fallbacks.get(name).cloned().unwrap_or("none".into());
+                [format!(":{name}:"), fallback]
+            })
+            .collect();
         Ok(Reply::Content {
-            content: MISSING_DESK.into(),
+            content: render_table(&["Custom emoji", "Fallback"], &rows),
         })
     }
 
-    /// Testing function to lookup a desk by a Zulip username
-    async fn _cmd_test_lookup_desk(&self, zulip_username: String) -> Result<Reply> {
-        debug!("Command::TestLookupDesk -> zulip_username = {zulip_username}");
-        let parsed = self.parse_zulip_username(&zulip_username);
-        let maybe_virtual_rc_username = self.lookup_corrected_name(&parsed);
+    /// `admin emoji_fallback :name: {emoji}` - Sets or removes the fallback of a custom emoji
+    async fn cmd_admin_set_emoji_fallback(
+        &self,
+        name: String,
+        fallback: Option<String>,
+    ) -> Result<Reply> {
+        let is_realm_emoji = self
+            .custom_emojis
+            .read()
+            .map_or(true, |c| c.is_empty() || c.contains(&name));
+        let content = match fallback {
+            Some(_) if !is_realm_emoji => {
+                format!("`:{name}:` is not a custom emoji of this Zulip realm")
+            }
+            Some(fallback) => {
+                let reply = format!("Virtual RC desks will show {fallback} for :{name}:");
+                self.emoji_fallbacks
+                    .update(|f| f.0.insert(name, fallback))?;
+                reply
+            }
+            None => match self.emoji_fallbacks.update(|f| f.0.remove(&name))? {
+                Some(_) => format!("Removed the fallback of :{name}:"),
+                None => format!("`:{name}:` has no fallback"),
+            },
+        };
+        Ok(Reply::Content { content })
+    }
 
-        if let Ok(desk_owners) = self.desk_owners.read() {
-            let desk_id = desk_owners
-                .get(&maybe_virtual_rc_username)
-                .map(|(id, _pos)| *id);
-            return Ok(Reply::Content {
-            content: format!(
-                "**Looking up your Virtual RC desk:**\n* Parsed Zulup username: `{:?}`\n* Virtual RC username match: `{:?}`\n* Virtual RC desk id: `{:?}`",
-                parsed,
-                maybe_virtual_rc_username,
-                desk_id
-            ),
-        });
+    /// Sends the bot to the known home position
+    pub async fn send_bot_home(&self) -> Result<()> {
+        if self.dry_run {
+            info!("dry run -> skipped sending the bot home");
+            return Ok(());
         }
+        let _span = logging::span("send_bot_home");
+        let mut req = UpdateBotRequest::default();
+        req.x = Some(self.home.x);
+        req.y = Some(self.home.y);
+        self.rc.update_bot(req).await?;
+        Ok(())
+    }
 
-        return Ok(Reply::Content {
-            content: format!("**Did not find a desk associated with your Zulip username**"),
-        });
+    /// Whether status commands only preview statuses
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
     }
 
-    /// Testing function that sends status bot to its home location
-    async fn _cmd_test_send_home(&self) -> Result<Reply> {
-        match self.send_bot_home().await {
-            Ok(_) => Ok(Reply::Content {
-                content: "Sent bot home".into(),
-            }),
-            Err(e) => Ok(Reply::Content {
-                content: format!("Failed to to send bot home: {e}"),
-            }),
+    /// Reads from the cached snapshot of desks
+    fn read_desks<R>(&self, f: impl FnOnce(&[Desk]) -> R) -> R {
+        f(&self.desks.load())
+    }
+
+    /// Reads from the emoji table. An unavailable lock reads as an empty table
+    fn read_emojis<R>(&self, f: impl FnOnce(&EmojiTable) -> R) -> R {
+        match self.emojis.read() {
+            Ok(emojis) => f(&emojis),
+            Err(_) => f(&EmojiTable::default()),
         }
     }
 
-    /// Testing function that sends Status Bot to any location
-    async fn _cmd_test_send_pos(&self) -> Result<Reply> {
-        todo!()
+    /// The Zulip alias (`:crab:`) of a unicode emoji. Falls back to the unicode emoji itself
+    fn zulip_emoji(&self, emoji: &str) -> String {
+        self.read_emojis(|t| t.zulip_alias(emoji).cloned())
+            .unwrap_or_else(|| emoji.into())
     }
 
-    /// Sends the bot to the known home position
-    async fn send_bot_home(&self) -> Result<UpdateBotResponse> {
-        let mut req = UpdateBotRequest::default();
-        req.x = Some(self.home.x);
-        req.y = Some(self.home.y);
-        self.rc.update_bot(req).await
+    /// Renders desks as a Zulip markdown table of name, emoji, status, and expiration
+    fn render_desks(&self, desks: &[&Desk]) -> String {
+        let now = OffsetDateTime::now_utc();
+        let rows = desks
+            .iter()
+            .map(|desk| {
+                let name = desk.owner.as_ref().map_or("", |o| o.name.as_str()).into();
+                if !has_active_status(desk, now) {
+                    return [name, "".into(), "".into(), "".into()];
+                }
+                let emoji = desk
+                    .emoji
+                    .as_ref()
+                    .map_or(String::new(), |e| self.zulip_emoji(e));
+                let status = desk.status.clone().unwrap_or_default();
+                let expires_at = desk.expires_at.map_or(String::new(), |dt| {
+                    dt.format(&Iso8601::DEFAULT)
+                        .map_or(String::new(), |ts| format!("<time:{ts}>"))
+                });
+                [name, emoji, status, expires_at]
+            })
+            .collect::<Vec<[String; 4]>>();
+        render_table(&["Name", "Emoji", "Status", "Expires"], &rows)
     }
 
     /// Given a recurse Zulip usernmae e.g. Jacob (Jake) Young (he/him) (F2'23)
@@ -418,72 +1596,335 @@ impl Bot {
         replaced_name.into()
     }
 
+    /// Removes mentions of Status Bot (e.g. `@**Status Bot**`, `@_**Status Bot|123**`) from a
+    /// message so that what remains can be parsed as a command
+    fn strip_mention(&self, message: &str, bot_full_name: &str) -> String {
+        let re_mention = Regex::new(&format!(
+            r"@_?\*\*{}(\|\d+)?\*\*",
+            regex::escape(bot_full_name)
+        ))
+        .unwrap();
+        re_mention.replace_all(message, "").trim().into()
+    }
+
     /// Parses the input message from the user into one of the known Status Bot commands.
-    /// If no command can be parsed, the help command is run showing help text
-    fn parse_cmd(&self, message: &str) -> Command {
-        // If the message is empty or entirely whitespace, the iterator will yield None
-        // split_whitespace() will also handle \t \n and other unicode whitespaces
-        let mut splits = message.split_whitespace();
-        if let Some(first) = splits.next() {
-            debug!("Parse Command -> first = {first}");
-            return match first {
-                "help" => Command::Help,
-                "show" => Command::Show,
-                // TODO: Enable clear when RC API gets fixed
-                // "clear" => Command::Clear,
-                // "feedback" => {
-                //     let feedback = Self::parse_feedback(splits);
-                //     match feedback.len() {
-                //         0 => Command::Help,
-                //         _ => Command::Feedback(feedback),
-                //     }
-                // }
-                "status" => {
-                    // Check if we received any of the optional arguments for status
-                    let input = Self::fold_splits(splits.clone());
-                    let mut peekable = splits.by_ref().peekable();
-                    let first = peekable.peek();
-                    match first {
-                        Some(_) => {
-                            let status = self.parse_status(input);
-                            Command::Status(status)
-                        }
-                        // We only got `status` with no arguments
-                        None => Command::Help,
+    /// An empty message is the help command. Anything else which cannot be parsed is a
+    /// [`ParseError`] telling the user what went wrong
+    fn parse_cmd(&self, message: &str) -> std::result::Result<Command, ParseError> {
+        let mut args = Args::new(message);
+        let Some(first) = args.next()? else {
+            // Empty mesage, entirely whitespace
+            return Ok(Command::Help);
+        };
+        let first = first.to_lowercase();
+        let name = resolve_alias(&first);
+        debug!("Parse Command -> first = {first}, name = {name}");
+        let command = match name {
+            "help" => {
+                let path = args.rest();
+                match find(&path) {
+                    _ if path.is_empty() => Command::Help,
+                    Some((parent, spec)) if spec.enabled => {
+                        let path = parent.map_or(spec.name.to_string(), |parent| {
+                            format!("{} {}", parent.name, spec.name)
+                        });
+                        Command::HelpFor(path)
+                    }
+                    _ => {
+                        let first = path.split_whitespace().next().unwrap_or_default();
+                        return Err(ParseError::Unknown {
+                            suggestion: self.suggest(&first.to_lowercase()),
+                            command: path,
+                        });
+                    }
+                }
+            }
+            "show" => Command::Show,
+            // TODO: Enable clear when RC API gets fixed
+            // "clear" => Command::Clear,
+            // "feedback" => {
+            //     let feedback = Self::parse_feedback(&mut args);
+            //     match feedback.len() {
+            //         0 => Command::Help,
+            //         _ => Command::Feedback(feedback),
+            //     }
+            // }
+            "clear" | "feedback" => Command::Help,
+            // Only a mention after `for` sets someone else's status, `status for lunch` is a status
+            "status" if is_status_for(&args) => {
+                args.next_if_eq("for");
+                let input = args.rest();
+                match Mention::parse(&input) {
+                    Some((owner, status)) if !status.is_empty() => {
+                        let (status, warnings) = self.parse_status(status.into());
+                        Command::StatusFor(owner, status, warnings)
+                    }
+                    _ => {
+                        return Err(ParseError::usage(
+                            "status for",
+                            "Tell Status Bot what their status is",
+                        ))
                     }
                 }
-                "set_name" => {
-                    let input = Self::fold_splits(splits.clone());
-                    Command::SetName(input)
+            }
+            "status" | "preview" => {
+                let is_preview = name == "preview" || args.next_if_eq("--preview");
+                if args.is_empty() {
+                    return Err(ParseError::usage(
+                        if is_preview { "preview" } else { "status" },
+                        "Tell Status Bot what your status is",
+                    ));
                 }
-                "clear_name" => Command::ClearName,
-                // Testing Commands (hidden)
-                "test_missing_desk" => Command::TestMissingDesk,
-                "test_lookup_desk" => {
-                    let zulip_username = Self::fold_splits(splits);
-                    Command::TestLookupDesk(zulip_username)
+                let (status, warnings) = self.parse_status(args.rest());
+                match is_preview {
+                    true => Command::Preview(status, warnings),
+                    false => Command::Status(status, warnings),
                 }
-                "test_send_home" => Command::TestSendHome,
-                // Any other words
-                _ => Command::Help,
-            };
+            }
+            "set_name" => match args.rest() {
+                name if name.is_empty() => {
+                    return Err(ParseError::usage(
+                        "set_name",
+                        "Tell Status Bot your Virtual RC name",
+                    ))
+                }
+                name => Command::SetName(name),
+            },
+            "clear_name" => Command::ClearName,
+            "desk" => match args.next()?.map(|id| id.parse::<usize>()) {
+                Some(Ok(desk_id)) => Command::Desk(desk_id),
+                _ => {
+                    return Err(ParseError::usage(
+                        "desk",
+                        "`{desk id}` must be the id of one of your desks",
+                    ))
+                }
+            },
+            "emoji" => match args.rest() {
+                query if query.is_empty() => {
+                    return Err(ParseError::usage(
+                        "emoji",
+                        "Tell Status Bot which emoji to look for",
+                    ))
+                }
+                query => Command::Emoji(query),
+            },
+            "who" => {
+                let input = args.rest();
+                match input.to_lowercase().as_str() {
+                    "" => return Err(ParseError::usage("who", "Tell Status Bot who to look for")),
+                    "is at their desk" => Command::WhoIsAtDesk,
+                    _ => Command::Who(input),
+                }
+            }
+            "team" => Command::Team(args.rest().to_lowercase()),
+            "digest" => match args.next()?.map(|arg| arg.to_lowercase()).as_deref() {
+                Some("on") => Command::Digest(true),
+                Some("off") => Command::Digest(false),
+                _ => return Err(ParseError::usage("digest", "Choose `on` or `off`")),
+            },
+            "pair" => {
+                let (topic, duration) = parse_pair_args(&args.rest());
+                match topic.is_empty() {
+                    true => {
+                        return Err(ParseError::usage(
+                            "pair",
+                            "Tell others what you would like to pair on",
+                        ))
+                    }
+                    false => Command::Pair(topic, duration),
+                }
+            }
+            "pairs" => Command::Pairs,
+            "delegate" | "revoke" => match (name, Mention::parse_or_name(&args.rest())) {
+                ("delegate", Some(delegate)) => Command::Delegate(delegate),
+                ("revoke", Some(delegate)) => Command::Revoke(delegate),
+                ("delegate", None) => {
+                    return Err(ParseError::usage(
+                        "delegate",
+                        "Mention who may set your status",
+                    ))
+                }
+                _ => {
+                    return Err(ParseError::usage(
+                        "revoke",
+                        "Mention who may no longer set your status",
+                    ))
+                }
+            },
+            "history" => match args.next()?.map(|n| n.parse::<usize>()) {
+                None => Command::History(DEFAULT_HISTORY_LENGTH),
+                Some(Ok(n)) if n > 0 => Command::History(n.min(MAX_HISTORY_LENGTH)),
+                Some(_) => {
+                    return Err(ParseError::usage(
+                        "history",
+                        "`{n}` must be a number greater than 0",
+                    ))
+                }
+            },
+            "reuse" => match args.next()?.map(|n| n.parse::<usize>()) {
+                Some(Ok(n)) if n > 0 => Command::Reuse(n),
+                _ => {
+                    return Err(ParseError::usage(
+                        "reuse",
+                        "`{n}` must be a number greater than 0, see `history` for the numbers",
+                    ))
+                }
+            },
+            "export" => match args.next()?.map(|format| format.to_lowercase()).as_deref() {
+                None | Some("json") => Command::Export(ExportFormat::Json),
+                Some("csv") => Command::Export(ExportFormat::Csv),
+                Some(_) => return Err(ParseError::usage("export", "Choose `json` or `csv`")),
+            },
+            "team_add" | "team_remove" => {
+                let command = match name {
+                    "team_add" => "team_add",
+                    _ => "team_remove",
+                };
+                let group = args.next()?.map(|g| g.to_lowercase()).unwrap_or_default();
+                let rc_username = args.rest();
+                match (command, group.is_empty() || rc_username.is_empty()) {
+                    (_, true) => {
+                        return Err(ParseError::usage(
+                            command,
+                            "Tell Status Bot the team and the Virtual RC name",
+                        ))
+                    }
+                    ("team_add", false) => Command::TeamAdd(group, rc_username),
+                    (_, false) => Command::TeamRemove(group, rc_username),
+                }
+            }
+            // Maintainer Commands (hidden)
+            "admin" => Command::Admin(self.parse_admin_cmd(&mut args)?),
+            // Any other words
+            _ => {
+                return Err(ParseError::Unknown {
+                    suggestion: self.suggest(&first),
+                    command: first,
+                })
+            }
         };
+        Ok(command)
+    }
+
+    /// Suggests a command for a typo. Only commands everyone can run are suggested
+    fn suggest(&self, word: &str) -> Option<&'static str> {
+        suggest(word, |spec| {
+            self.policy.required_role(spec.name) == Role::User
+        })
+    }
 
-        // Empty mesage, entirely whitespace
-        Command::Help
+    /// Parses the words following `admin` into one of the maintainer commands.
+    /// If no sub command is given, the admin help text is shown
+    fn parse_admin_cmd(&self, args: &mut Args) -> std::result::Result<AdminCommand, ParseError> {
+        let sub_command = args.next()?.map(|arg| arg.to_lowercase());
+        let admin_command = match sub_command.as_deref() {
+            Some("lookup") => match args.rest() {
+                name if name.is_empty() => {
+                    return Err(ParseError::usage(
+                        "admin lookup",
+                        "Tell Status Bot which Zulip name to look up",
+                    ))
+                }
+                name => AdminCommand::Lookup(name),
+            },
+            Some("set_name") => match (args.next()?, args.next()?) {
+                (Some(zulip), Some(rc))
+                    if !zulip.trim().is_empty() && !rc.trim().is_empty() && args.is_empty() =>
+                {
+                    AdminCommand::SetName(zulip.trim().into(), rc.trim().into())
+                }
+                _ => {
+                    return Err(ParseError::usage(
+                        "admin set_name",
+                        "Tell Status Bot the Zulip name and the Virtual RC name, quoting names with spaces",
+                    ))
+                }
+            },
+            Some("release_desk") => match args.next()?.and_then(|id| id.parse::<usize>().ok()) {
+                Some(desk_id) => AdminCommand::ReleaseDesk(desk_id),
+                None => {
+                    return Err(ParseError::usage(
+                        "admin release_desk",
+                        "Tell Status Bot which desk id to release",
+                    ))
+                }
+            },
+            Some("refresh_desks") => AdminCommand::RefreshDesks,
+            Some("send_home") => AdminCommand::SendHome,
+            Some("move") => {
+                let x = args.next()?.and_then(|x| x.parse::<usize>().ok());
+                let y = args.next()?.and_then(|y| y.parse::<usize>().ok());
+                match (x, y) {
+                    (Some(x @ GRID_X_MIN..=GRID_X_MAX), Some(y @ GRID_Y_MIN..=GRID_Y_MAX)) => {
+                        AdminCommand::Move(Position { x, y })
+                    }
+                    _ => {
+                        return Err(ParseError::usage(
+                            "admin move",
+                            format!("`{{x}}` must be between {GRID_X_MIN} and {GRID_X_MAX} and `{{y}}` between {GRID_Y_MIN} and {GRID_Y_MAX}"),
+                        ))
+                    }
+                }
+            }
+            Some("stats") => AdminCommand::Stats,
+            Some("missing_desk") => AdminCommand::MissingDesk,
+            Some("reload_emojis") => AdminCommand::ReloadEmojis,
+            Some("emoji_fallback") => {
+                let Some(custom_emoji) = args.next()? else {
+                    return Ok(AdminCommand::EmojiFallbacks);
+                };
+                let name = emoji::alias_name(&custom_emoji).unwrap_or(&custom_emoji);
+                let fallback = match args.next()? {
+                    Some(none) if none.eq_ignore_ascii_case("none") => None,
+                    Some(fallback) => match self.parse_unicode_emoji(&fallback) {
+                        Some(fallback) => Some(fallback),
+                        None => {
+                            return Err(ParseError::usage(
+                                "admin emoji_fallback",
+                                format!("{fallback} is not a unicode emoji"),
+                            ))
+                        }
+                    },
+                    None => {
+                        return Err(ParseError::usage(
+                            "admin emoji_fallback",
+                            "Tell Status Bot the fallback, or `none` to remove it",
+                        ))
+                    }
+                };
+                AdminCommand::SetEmojiFallback(name.into(), fallback)
+            }
+            _ => AdminCommand::Help,
+        };
+        Ok(admin_command)
     }
 
     /// Collects all of the remaining words back into a string
-    fn parse_feedback(splits: SplitWhitespace<'_>) -> String {
-        Self::fold_splits(splits)
+    fn parse_feedback(args: &mut Args) -> String {
+        args.rest()
     }
 
-    /// Handles the different valid combinations to construct a  [`Status`]
-    fn parse_status(&self, input: String) -> Status {
+    /// Handles the different valid combinations to construct a  [`Status`]. Parts of the input
+    /// that had to be left out are returned as warnings
+    fn parse_status(&self, input: String) -> (Status, Vec<StatusWarning>) {
+        let mut warnings = Vec::new();
         let mut maybe_emoji: Option<String> = None;
         let mut maybe_status: Option<String> = None;
         let mut maybe_expires_at: Option<OffsetDateTime> = None;
-        let re_status = format!(r"{}\s?{}\s?{}", RE_EMOJI, RE_STATUS, RE_TIME);
+        // A status can start with a unicode emoji (🦀) instead of an alias (:crab:). The rest of
+        // the input is then only made of the status and expiration
+        let (re_status, input) = match emoji::split_leading(&input) {
+            Some((unicode_emoji, rest)) => {
+                debug!("unicode_emoji = {unicode_emoji}");
+                maybe_emoji = Some(unicode_emoji);
+                (format!(r"{}\s?{}", RE_STATUS, RE_TIME), rest.to_string())
+            }
+            None => (
+                format!(r"{}\s?{}\s?{}", RE_EMOJI, RE_STATUS, RE_TIME),
+                input,
+            ),
+        };
         let re_status = Regex::new(&re_status).unwrap();
 
         return match re_status.captures(&input) {
@@ -491,12 +1932,19 @@ impl Bot {
                 if let Some(maybe_alias) = caps.name("emoji") {
                     let maybe_alias = maybe_alias.as_str().trim();
                     debug!("maybe_alias = {maybe_alias}");
-                    maybe_emoji = self.parse_emoji(maybe_alias);
-                    // This means we couldn't find a matching emoji alias. Either the user gave us
-                    // a custom emoji, mispelled it, or we have an out of date zulip emoji.json
-                    if maybe_emoji.is_none() {
-                        debug!("failed to find alias = {maybe_alias} in out ZulipEmoji");
-                    }
+                    // An error means we couldn't find a matching emoji alias. Either the user gave
+                    // us a custom emoji, mispelled it, or we have an out of date zulip emoji.json
+                    maybe_emoji = match self.parse_emoji(maybe_alias) {
+                        Ok(emoji) => Some(emoji),
+                        Err(suggestions) => {
+                            debug!("failed to find alias = {maybe_alias} in out ZulipEmoji, suggestions = {suggestions:?}");
+                            warnings.push(StatusWarning::UnknownEmoji {
+                                alias: maybe_alias.into(),
+                                suggestions,
+                            });
+                            None
+                        }
+                    };
                 }
 
                 if let Some(s) = caps.name("status") {
@@ -506,94 +1954,235 @@ impl Bot {
 
                 if let Some(maybe_iso8061) = caps.name("iso8061") {
                     let maybe_iso8061 = maybe_iso8061.as_str().trim();
-                    if let Ok(date_time) = OffsetDateTime::parse(maybe_iso8061, &Iso8601::DEFAULT) {
-                        maybe_expires_at = Some(date_time);
+                    match OffsetDateTime::parse(maybe_iso8061, &Iso8601::DEFAULT) {
+                        Ok(date_time) => maybe_expires_at = Some(date_time),
+                        Err(e) => {
+                            debug!("failed to parse time = {maybe_iso8061}, err = {e}");
+                            warnings.push(StatusWarning::InvalidTime {
+                                time: maybe_iso8061.into(),
+                                defaulted: maybe_status.is_some(),
+                            });
+                        }
                     }
                 }
 
-                Status::from((maybe_emoji, maybe_status, maybe_expires_at))
+                let status = Status::from((maybe_emoji, maybe_status, maybe_expires_at));
+                (status, warnings)
             }
-            None => Status::default(),
+            None => (Status::default(), warnings),
         };
     }
 
-    /// Given an input string, attempts to parse the zulip alias :apple: to a unicode character codepoint
-    /// using a custom emoji.json file and the emojic crate
-    fn parse_emoji(&self, maybe_alias: &str) -> Option<String> {
-        let mut result: Option<String> = None;
-        // We may get multiple aliases for this emoji (E.g. "first,second")
-        if let Some(aliases) = self.emojis.0.get(maybe_alias) {
-            // If there is one alias and no ',' we will only get one item
-            for alias in aliases.split(COMMA) {
-                // If we already successfully set the result's emoji from a previous
-                // iteration, then we should not overwrite with None if the second alias is
-                // incorrect for some reason
-                if result.is_none() {
-                    let emoji = emojic::parse_alias(alias);
-                    result = emoji.map_or(None, |e| Some(e.grapheme.into()));
-                    debug!("EMOJI MATCH = {result:?}");
-                }
-            }
+    /// Given an input string, attempts to parse the zulip alias :apple: to a unicode character codepoint.
+    /// Custom emojis with a fallback are kept as their alias (:sadparrot:).
+    ///
+    /// Returns the closest emojis (`:crab:`) when the alias is unknown
+    fn parse_emoji(&self, maybe_alias: &str) -> std::result::Result<String, Vec<String>> {
+        let custom_emoji = format!(":{maybe_alias}:");
+        if let Some(emoji) = self.parse_unicode_emoji(maybe_alias) {
+            return Ok(emoji);
+        }
+        if self.emoji_fallback(&custom_emoji).is_some() {
+            return Ok(custom_emoji);
+        }
+        let suggestions = self.read_emojis(|t| t.search(maybe_alias, Self::EMOJI_SUGGESTIONS));
+        Err(suggestions
+            .into_iter()
+            .map(|found| found.alias.unwrap_or(found.unicode))
+            .collect())
+    }
+
+    /// The unicode emoji Virtual RC desks show for a custom emoji (:sadparrot:)
+    fn emoji_fallback(&self, custom_emoji: &str) -> Option<String> {
+        let name = emoji::alias_name(custom_emoji)?;
+        self.emoji_fallbacks.read(|f| f.get(name).cloned())
+    }
+
+    /// Parses a zulip alias (apple) to a unicode emoji using a custom emoji.json file and the emojic
+    /// crate. Standard aliases unknown to Zulip (red_apple) and unicode emojis (🍎) are accepted too
+    fn parse_unicode_emoji(&self, maybe_alias: &str) -> Option<String> {
+        if let Some((unicode_emoji, "")) = emoji::split_leading(maybe_alias) {
+            return Some(unicode_emoji);
+        }
+        let maybe_alias = emoji::alias_name(maybe_alias).unwrap_or(maybe_alias);
+        let result = self.read_emojis(|t| t.unicode(maybe_alias).cloned());
+        debug!("EMOJI MATCH = {result:?}");
+        match result {
+            // Emojis newer than emojic come from Zulip's own table, so they are trusted
+            Some(unicode) => Some(emoji::validate(&unicode).unwrap_or(unicode)),
+            None => emoji::parse_standard_alias(maybe_alias).and_then(|e| emoji::validate(&e)),
         }
-        result
     }
 
     /// Looks up the associated desk for the zulip username.
     /// If this user provided a username correction then
     /// the corrected name will be used to lookup the desk_id instead.
-    fn lookup_desk_id(&self, zulip_username: &str) -> Option<(usize, Position)> {
+    ///
+    /// When no desk is found and the cached desks are stale, they are refreshed and the lookup is
+    /// tried again, the desk may have been claimed since the last refresh
+    ///
+    /// When the name owns several desks, the desk the user chose with `desk` is used
+    async fn lookup_desk_id(&self, zulip_id: u64, zulip_username: &str) -> DeskMatch {
+        let mut span = logging::span("desk_lookup");
+        let mut desk = self.find_desk(zulip_id, zulip_username);
+        let is_stale =
+            || desks_are_stale(self.stats.last_desk_refresh(), OffsetDateTime::now_utc());
+        if desk == DeskMatch::None && is_stale() {
+            let _refreshing = self.desk_refresh.lock().await;
+            // Another lookup may have refreshed the desks while this one waited
+            if is_stale() {
+                debug!("bot -> lookup_desk_id -> no desk for zulip_username = {zulip_username} in stale desks, refreshing");
+                span.attr("refreshed", true);
+                if let Err(e) = self.cache_desk_owners().await {
+                    warn!("Failed to refresh the desks for a lookup. Err = {e}");
+                }
+            }
+            desk = self.find_desk(zulip_id, zulip_username);
+        }
+        span.attr("found", matches!(desk, DeskMatch::One(..)));
+        span.attr("ambiguous", matches!(desk, DeskMatch::Ambiguous(_)));
+        desk
+    }
+
+    /// Looks up the desk of the zulip username in the cached desks
+    fn find_desk(&self, zulip_id: u64, zulip_username: &str) -> DeskMatch {
+        let candidates = self.desk_candidates(zulip_username);
+        self.desk_choices
+            .read(|choices| choices.pick(zulip_id, &candidates))
+    }
+
+    /// Every cached desk owned by the (corrected) Virtual RC name of the zulip username
+    fn desk_candidates(&self, zulip_username: &str) -> Vec<(usize, Position)> {
         let zulip_username = self.parse_zulip_username(zulip_username);
         let maybe_virtual_rc_username = self.lookup_corrected_name(&zulip_username);
 
-        if let Ok(desk_owners) = self.desk_owners.try_read() {
-            desk_owners
-                .get(&maybe_virtual_rc_username)
-                .map(|pair| pair.clone())
-        } else {
-            return None;
-        }
+        self.desk_owners
+            .load()
+            .get(&maybe_virtual_rc_username)
+            .cloned()
+            .unwrap_or_default()
     }
 
     /// Looks up the a name correction provided by the user if they called the set_name command
     /// If no name correction is found, it turnes the original username
     fn lookup_corrected_name<'a>(&'a self, zulip_username: &'a str) -> String {
-        if let Ok(corrected_names) = self.corrected_names.try_read() {
-            corrected_names
-                .get(zulip_username)
-                .map_or_else(|| zulip_username.into(), |new_name| new_name.clone())
-        } else {
-            zulip_username.into()
-        }
+        self.corrected_names
+            .load()
+            .get(zulip_username)
+            .map_or_else(|| zulip_username.into(), |new_name| new_name.clone())
     }
+}
 
-    /// Collects a SplitsIterator into a String
-    fn fold_splits(splits: SplitWhitespace) -> String {
-        splits
-            .fold(String::new(), |mut a, b| {
-                a.reserve(b.len() + 1);
-                a.push_str(b);
-                a.push_str(" ");
-                a
-            })
-            .trim_end()
-            .into()
-    }
+/// How a Zulip username was matched to a desk
+#[derive(Debug)]
+pub struct DeskLookup {
+    /// The username without pronouns and batch, e.g. Jacob Young
+    pub parsed: String,
+    /// The Virtual RC name it was matched as, after `set_name` corrections
+    pub name: String,
+    /// Every desk owned by the name, more than one when the user has to choose with `desk`
+    pub desks: Vec<(usize, Position)>,
+}
+
+/// The Zulip user who sent a message to Status Bot
+#[derive(Debug, Clone)]
+pub struct Sender {
+    /// The Zulip user id
+    pub id: u64,
+    /// The Zulip full name, e.g. Jacob Young (he/him) (F2'23)
+    pub full_name: String,
 }
 
 /// A Command Status Bot knows about
 #[derive(Debug, PartialEq, Eq)]
 pub enum Command {
-    Status(Status),
+    Status(Status, Vec<StatusWarning>),
+    /// A status shown without being set, with `preview` or `status --preview`
+    Preview(Status, Vec<StatusWarning>),
     Show,
     Clear,
     Feedback(String),
     SetName(String),
     ClearName,
+    /// Choose the desk of the sender among the desks owned by their name
+    Desk(usize),
+    /// Search emojis by alias or name
+    Emoji(String),
+    Who(String),
+    WhoIsAtDesk,
+    Team(String),
+    TeamAdd(String, String),
+    TeamRemove(String, String),
+    Digest(bool),
+    Pair(String, Option<Duration>),
+    Pairs,
+    History(usize),
+    Reuse(usize),
+    Export(ExportFormat),
+    Delegate(Mention),
+    Revoke(Mention),
+    /// Set the status of someone who delegated their desk to the sender
+    StatusFor(Mention, Status, Vec<StatusWarning>),
+    Help,
+    /// Explain a single command or sub command, e.g. `status for`
+    HelpFor(String),
+    // Maintainer Commands (hidden)
+    Admin(AdminCommand),
+}
+
+impl Command {
+    /// The name used to type the command, which is also its name in the authorization [`Policy`]
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::Status(..) => "status",
+            Command::Preview(..) => "preview",
+            Command::Show => "show",
+            Command::Clear => "clear",
+            Command::Feedback(_) => "feedback",
+            Command::SetName(_) => "set_name",
+            Command::ClearName => "clear_name",
+            Command::Desk(_) => "desk",
+            Command::Emoji(_) => "emoji",
+            Command::Who(_) | Command::WhoIsAtDesk => "who",
+            Command::Team(_) => "team",
+            Command::TeamAdd(..) => "team_add",
+            Command::TeamRemove(..) => "team_remove",
+            Command::Digest(_) => "digest",
+            Command::Pair(..) => "pair",
+            Command::Pairs => "pairs",
+            Command::History(_) => "history",
+            Command::Reuse(_) => "reuse",
+            Command::Export(_) => "export",
+            Command::Delegate(_) => "delegate",
+            Command::Revoke(_) => "revoke",
+            Command::StatusFor(..) => "status_for",
+            Command::Help | Command::HelpFor(_) => "help",
+            Command::Admin(_) => "admin",
+        }
+    }
+}
+
+/// A maintainer command, prefixed with `admin`
+#[derive(Debug, PartialEq, Eq)]
+pub enum AdminCommand {
     Help,
-    // Testing Commands (hidden)
-    TestMissingDesk,
-    TestLookupDesk(String),
-    TestSendHome,
+    /// Explain how a Zulip username is matched to a desk
+    Lookup(String),
+    /// Set the Virtual RC username of another Zulip user
+    SetName(String, String),
+    /// Forget who chose a desk with the `desk` command
+    ReleaseDesk(usize),
+    RefreshDesks,
+    SendHome,
+    Move(Position),
+    Stats,
+    MissingDesk,
+    /// Load the emoji table again from EMOJI_TABLE
+    ReloadEmojis,
+    /// List the custom emojis of the realm and their fallbacks
+    EmojiFallbacks,
+    /// Set the unicode emoji shown on desks for a custom emoji. None removes it
+    SetEmojiFallback(String, Option<String>),
 }
 
 /// Reply represents the Bot's response message to Zulip's outgoing webhook.
@@ -632,7 +2221,7 @@ pub enum Reply {
 ///
 /// * Setting a status with an expiration time:
 ///     * `status :bento box: Let's get lunch soon. All aboard the lunch train! <time:2023-09-29T12:00:00-06:00>`
-#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq, Clone)]
 pub struct Status {
     /// An emoji for the status.
     /// Default: 💻
@@ -663,6 +2252,92 @@ pub struct Status {
     pub expires_at: Option<time::OffsetDateTime>,
 }
 
+/// A part of a status command that could not be used, reported back to the user
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StatusWarning {
+    /// The emoji alias is not a Zulip, standard, or custom emoji with a fallback
+    UnknownEmoji {
+        alias: String,
+        suggestions: Vec<String>,
+    },
+    /// The `<time:...>` is not an ISO8601 timestamp. The default expiration is used when the
+    /// status has text
+    InvalidTime { time: String, defaulted: bool },
+}
+
+impl Display for StatusWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StatusWarning::UnknownEmoji { alias, suggestions } if suggestions.is_empty() => {
+                write!(f, "emoji `:{alias}:` not recognised")
+            }
+            StatusWarning::UnknownEmoji { alias, suggestions } => write!(
+                f,
+                "emoji `:{alias}:` not recognised, did you mean {}?",
+                suggestions.join(", ")
+            ),
+            StatusWarning::InvalidTime {
+                time,
+                defaulted: true,
+            } => write!(
+                f,
+                "couldn't read time `{time}`, defaulted to {}m",
+                Status::DEFAULT_EXPIRES_AT.whole_minutes()
+            ),
+            StatusWarning::InvalidTime {
+                time,
+                defaulted: false,
+            } => write!(f, "couldn't read time `{time}`, the status does not expire"),
+        }
+    }
+}
+
+/// Explains why a status cannot be set, one problem per line
+fn invalid_status(errors: &[ValidationError]) -> String {
+    let problems: Vec<String> = errors.iter().map(|e| format!("* {e}")).collect();
+    format!(
+        "**:cross_mark: Status Bot cannot set this status**\n{}",
+        problems.join("\n")
+    )
+}
+
+/// Whether the arguments of `status` are `for` followed by a mention
+fn is_status_for(args: &Args) -> bool {
+    let mut peek = args.clone();
+    peek.next_if_eq("for") && Mention::parse(&peek.rest()).is_some()
+}
+
+fn invalid_status_reply(errors: &[ValidationError]) -> Reply {
+    Reply::Content {
+        content: invalid_status(errors),
+    }
+}
+
+/// Appends the warnings to a reply, one per line
+fn with_warnings(reply: Reply, warnings: &[StatusWarning]) -> Reply {
+    match reply {
+        Reply::Content { mut content } if !warnings.is_empty() => {
+            for warning in warnings {
+                content.push_str(&format!("\n:warning: {warning}"));
+            }
+            Reply::Content { content }
+        }
+        reply => reply,
+    }
+}
+
+/// A desk has an active status when it has a status or emoji that has not expired yet
+fn has_active_status(desk: &Desk, now: OffsetDateTime) -> bool {
+    (desk.status.is_some() || desk.emoji.is_some())
+        && desk.expires_at.is_none_or(|expires_at| expires_at > now)
+}
+
+/// The cached desks are stale when they were never fetched or fetched more than
+/// DESKS_STALE_AFTER seconds ago
+fn desks_are_stale(last_refresh: Option<OffsetDateTime>, now: OffsetDateTime) -> bool {
+    last_refresh.is_none_or(|at| now - at > Duration::seconds(DESKS_STALE_AFTER as i64))
+}
+
 impl Status {
     /// The deafult expiration time for a status (when none is provided)
     const DEFAULT_EXPIRES_AT: Duration = Duration::new(1800 /* 30 minutes */, 0);
@@ -725,11 +2400,22 @@ mod tests {
     use time::OffsetDateTime;
 
     use crate::bot::Status;
+    use crate::config::Config;
+    use crate::history::ExportFormat;
     use crate::load_env;
-    use crate::zulip::ZulipEmoji;
 
+    use super::AdminCommand;
     use super::Bot;
     use super::Command;
+    use super::DeskMatch;
+    use super::Reply;
+    use super::Sender;
+    use crate::consts::ZULIP_BOT_API_TOKEN;
+    use crate::delegation::Mention;
+    use crate::parser::ParseError;
+    use crate::rc::{Desk, Position};
+    use crate::registry::find;
+    use crate::zulip::OutgoingWebhook;
 
     use once_cell::sync::OnceCell;
     static BOT: OnceCell<Bot> = OnceCell::new();
@@ -737,16 +2423,16 @@ mod tests {
 
     pub fn init() {
         LOGGER.call_once(|| {
-            pretty_env_logger::init();
+            crate::logging::init();
         });
         // Load the .env file based
-        load_env();
+        let _ = load_env();
         // Create a new Bot
-        let file = include_str!("zulip.json");
-        let emoji: ZulipEmoji = serde_json::from_str(file).unwrap();
+        let emoji = crate::emoji::bundled_table();
         let https = HttpsConnector::new();
         let client = Client::builder().build::<_, hyper::Body>(https);
-        let bot = Bot::new(client, emoji);
+        let config = Config::load().expect("the test environment is a valid configuration");
+        let bot = Bot::new(client, emoji, config);
         // Intentionally do nothing with the error because
         // it's okay to attempt to set mulitple times
         let _ = BOT.set(bot);
@@ -756,26 +2442,195 @@ mod tests {
         BOT.get().expect("Bot was not initialized")
     }
 
+    /* Test Registry Examples */
+    #[test_case("status")]
+    #[test_case("preview")]
+    #[test_case("emoji")]
+    #[test_case("history")]
+    #[test_case("reuse")]
+    #[test_case("desk")]
+    #[test_case("export")]
+    #[test_case("delegate")]
+    #[test_case("revoke")]
+    #[test_case("who")]
+    #[test_case("team")]
+    #[test_case("team_add")]
+    #[test_case("team_remove")]
+    #[test_case("pair")]
+    #[test_case("digest")]
+    #[test_case("set_name")]
+    #[test_case("help")]
+    #[test_case("admin")]
+    fn test_registry_examples_parse(name: &str) {
+        init();
+        let bot = get_test_bot();
+        let (_, spec) = find(name).unwrap();
+        let sub_examples = spec.subcommands.iter().flat_map(|sub| sub.examples);
+        for example in spec.examples.iter().chain(sub_examples) {
+            assert!(bot.parse_cmd(example).is_ok(), "{example} does not parse");
+        }
+    }
+
     /* Test Command Splitting */
-    #[test_case("status" => Command::Help ; "test status command empty")]
+    #[test_case("status" => Err("status".into()) ; "test status command empty gives usage")]
     #[test_case("status :apple: watching the Apple keynote <time:2025-01-01T13:00:00-04:00>"
-        => Command::Status(Status{
+        => Ok(Command::Status(Status{
             emoji: Some(emojic::flat::RED_APPLE.grapheme.into()),
             status: Some("watching the Apple keynote".into()),
             expires_at: Some(datetime!(2025-01-01 13:00:00 -4))
-        })
+        }, vec![]))
         ; "test status command full")]
-    #[test_case("help" => Command::Help ; "test help command")]
-    #[test_case("show" => Command::Show ; "test show command")]
-    #[test_case("feedback" => Command::Help ; "test feedback empty gives help command")]
-    #[test_case("feedback this bot sucks" => Command::Help ; "test feedback")]
-    #[test_case("clear" => Command::Help ; "test clear command")]
-    #[test_case("random" => Command::Help ; "test invalid command gives help command")]
-    #[test_case("" => Command::Help ; "test empty input gives help command")]
-    fn test_commmand_splitting(input: &str) -> Command {
+    #[test_case("s :apple: watching the Apple keynote <time:2025-01-01T13:00:00-04:00>"
+        => Ok(Command::Status(Status{
+            emoji: Some(emojic::flat::RED_APPLE.grapheme.into()),
+            status: Some("watching the Apple keynote".into()),
+            expires_at: Some(datetime!(2025-01-01 13:00:00 -4))
+        }, vec![]))
+        ; "test status alias")]
+    #[test_case("Status :apple: watching the Apple keynote <time:2025-01-01T13:00:00-04:00>"
+        => Ok(Command::Status(Status{
+            emoji: Some(emojic::flat::RED_APPLE.grapheme.into()),
+            status: Some("watching the Apple keynote".into()),
+            expires_at: Some(datetime!(2025-01-01 13:00:00 -4))
+        }, vec![]))
+        ; "test command ignores case")]
+    #[test_case("status for @**Jacob Young** :apple: watching the Apple keynote <time:2025-01-01T13:00:00-04:00>"
+        => Ok(Command::StatusFor(
+            Mention { name: "Jacob Young".into(), id: None },
+            Status {
+                emoji: Some(emojic::flat::RED_APPLE.grapheme.into()),
+                status: Some("watching the Apple keynote".into()),
+                expires_at: Some(datetime!(2025-01-01 13:00:00 -4))
+            },
+            vec![]))
+        ; "test status for command")]
+    #[test_case("status for @**Jacob Young**" => Err("status for".into()) ; "test status for without status gives usage")]
+    #[test_case("status for the next hour I'm heads down <time:2025-01-01T13:00:00-04:00>"
+        => Ok(Command::Status(Status{
+            emoji: None,
+            status: Some("for the next hour I'm heads down".into()),
+            expires_at: Some(datetime!(2025-01-01 13:00:00 -4))
+        }, vec![]))
+        ; "test status starting with for without mention")]
+    #[test_case("stauts :apple: hi" => Err("unknown, did you mean status".into()) ; "test typo suggests a command")]
+    #[test_case("delegate @_**Jacob Young|123**" => Ok(Command::Delegate(Mention { name: "Jacob Young".into(), id: Some(123) })) ; "test delegate command")]
+    #[test_case("revoke Jacob Young" => Ok(Command::Revoke(Mention { name: "Jacob Young".into(), id: None })) ; "test revoke command with plain name")]
+    #[test_case("delegate" => Err("delegate".into()) ; "test delegate empty gives usage")]
+    #[test_case("help" => Ok(Command::Help) ; "test help command")]
+    #[test_case("?" => Ok(Command::Help) ; "test help alias")]
+    #[test_case("help status" => Ok(Command::HelpFor("status".into())) ; "test help for a command")]
+    #[test_case("help S" => Ok(Command::HelpFor("status".into())) ; "test help for an alias")]
+    #[test_case("help status for" => Ok(Command::HelpFor("status for".into())) ; "test help for a sub command")]
+    #[test_case("help clear" => Err("unknown".into()) ; "test help for a disabled command is unknown")]
+    #[test_case("help stauts" => Err("unknown, did you mean status".into()) ; "test help for a typo suggests a command")]
+    #[test_case("show" => Ok(Command::Show) ; "test show command")]
+    #[test_case("feedback" => Ok(Command::Help) ; "test feedback empty gives help command")]
+    #[test_case("feedback this bot sucks" => Ok(Command::Help) ; "test feedback")]
+    #[test_case("clear" => Ok(Command::Help) ; "test clear command")]
+    #[test_case("random" => Err("unknown".into()) ; "test invalid command gives unknown command")]
+    #[test_case("" => Ok(Command::Help) ; "test empty input gives help command")]
+    #[test_case("set_name" => Err("set_name".into()) ; "test set name empty gives usage")]
+    #[test_case("set_name Jake Young" => Ok(Command::SetName("Jake Young".into())) ; "test set name command")]
+    #[test_case("who" => Err("who".into()) ; "test who empty gives usage")]
+    #[test_case("preview" => Err("preview".into()) ; "test preview command empty gives usage")]
+    #[test_case("status --preview" => Err("preview".into()) ; "test status preview empty gives preview usage")]
+    #[test_case("preview :apple: watching the Apple keynote <time:2025-01-01T13:00:00-04:00>"
+        => Ok(Command::Preview(Status{
+            emoji: Some(emojic::flat::RED_APPLE.grapheme.into()),
+            status: Some("watching the Apple keynote".into()),
+            expires_at: Some(datetime!(2025-01-01 13:00:00 -4))
+        }, vec![]))
+        ; "test preview command")]
+    #[test_case("status --PREVIEW :apple: watching the Apple keynote <time:2025-01-01T13:00:00-04:00>"
+        => Ok(Command::Preview(Status{
+            emoji: Some(emojic::flat::RED_APPLE.grapheme.into()),
+            status: Some("watching the Apple keynote".into()),
+            expires_at: Some(datetime!(2025-01-01 13:00:00 -4))
+        }, vec![]))
+        ; "test status preview flag")]
+    #[test_case("emoji" => Err("emoji".into()) ; "test emoji command empty gives usage")]
+    #[test_case("emoji red apple" => Ok(Command::Emoji("red apple".into())) ; "test emoji command")]
+    #[test_case("who Jacob Young" => Ok(Command::Who("Jacob Young".into())) ; "test who command")]
+    #[test_case("who \"Jacob Young\"" => Ok(Command::Who("Jacob Young".into())) ; "test who command quoted")]
+    #[test_case("who is at their desk" => Ok(Command::WhoIsAtDesk) ; "test who is at their desk command")]
+    #[test_case("team" => Ok(Command::Team("".into())) ; "test team empty lists teams")]
+    #[test_case("team Rustaceans" => Ok(Command::Team("rustaceans".into())) ; "test team command")]
+    #[test_case("team_add rustaceans Jacob Young" => Ok(Command::TeamAdd("rustaceans".into(), "Jacob Young".into())) ; "test team add command")]
+    #[test_case("team_add \"Rust Club\" Jacob Young" => Ok(Command::TeamAdd("rust club".into(), "Jacob Young".into())) ; "test team add command with quoted group")]
+    #[test_case("team_add \"Rust Club Jacob Young" => Err("unterminated quote".into()) ; "test team add command with unterminated quote")]
+    #[test_case("team_add rustaceans" => Err("team_add".into()) ; "test team add without name gives usage")]
+    #[test_case("team_remove rustaceans Jacob Young" => Ok(Command::TeamRemove("rustaceans".into(), "Jacob Young".into())) ; "test team remove command")]
+    #[test_case("team_remove" => Err("team_remove".into()) ; "test team remove empty gives usage")]
+    #[test_case("digest on" => Ok(Command::Digest(true)) ; "test digest on command")]
+    #[test_case("desk 42" => Ok(Command::Desk(42)) ; "test desk command")]
+    #[test_case("desk forty" => Err("desk".into()) ; "test desk invalid id gives usage")]
+    #[test_case("digest off" => Ok(Command::Digest(false)) ; "test digest off command")]
+    #[test_case("digest" => Err("digest".into()) ; "test digest empty gives usage")]
+    #[test_case("pair rust async 2h" => Ok(Command::Pair("rust async".into(), Some(time::Duration::hours(2)))) ; "test pair command with duration")]
+    #[test_case("pair leetcode" => Ok(Command::Pair("leetcode".into(), None)) ; "test pair command without duration")]
+    #[test_case("pair" => Err("pair".into()) ; "test pair empty gives usage")]
+    #[test_case("pairs" => Ok(Command::Pairs) ; "test pairs command")]
+    #[test_case("admin" => Ok(Command::Admin(AdminCommand::Help)) ; "test admin empty gives admin help")]
+    #[test_case("admin lookup Jacob Young (he/him)" => Ok(Command::Admin(AdminCommand::Lookup("Jacob Young (he/him)".into()))) ; "test admin lookup command")]
+    #[test_case(r#"admin set_name "Jacob Young (he/him)" "Jake Young""# => Ok(Command::Admin(AdminCommand::SetName("Jacob Young (he/him)".into(), "Jake Young".into()))) ; "test admin set name command")]
+    #[test_case("admin set_name Jacob Jake" => Ok(Command::Admin(AdminCommand::SetName("Jacob".into(), "Jake".into()))) ; "test admin set name without quotes")]
+    #[test_case(r#"admin set_name "Jacob Young""# => Err("admin set_name".into()) ; "test admin set name without rc name gives usage")]
+    #[test_case("admin set_name Jacob Young (he/him) Jake" => Err("admin set_name".into()) ; "test admin set name unquoted names give usage")]
+    #[test_case(r#"admin set_name "Jacob Young" """# => Err("admin set_name".into()) ; "test admin set name empty rc name gives usage")]
+    #[test_case(r#"admin set_name "Jacob Young "# => Err("unterminated quote".into()) ; "test admin set name unterminated quote")]
+    #[test_case("admin release_desk 42" => Ok(Command::Admin(AdminCommand::ReleaseDesk(42))) ; "test admin release desk command")]
+    #[test_case("admin release_desk" => Err("admin release_desk".into()) ; "test admin release desk without id gives usage")]
+    #[test_case("admin release_desk abc" => Err("admin release_desk".into()) ; "test admin release desk bad id gives usage")]
+    #[test_case("admin refresh_desks" => Ok(Command::Admin(AdminCommand::RefreshDesks)) ; "test admin refresh desks command")]
+    #[test_case("admin send_home" => Ok(Command::Admin(AdminCommand::SendHome)) ; "test admin send home command")]
+    #[test_case("admin move 10 20" => Ok(Command::Admin(AdminCommand::Move(Position { x: 10, y: 20 }))) ; "test admin move command")]
+    #[test_case("admin move 10 2000" => Err("admin move".into()) ; "test admin move outside of grid gives usage")]
+    #[test_case("admin stats" => Ok(Command::Admin(AdminCommand::Stats)) ; "test admin stats command")]
+    #[test_case("admin reload_emojis" => Ok(Command::Admin(AdminCommand::ReloadEmojis)) ; "test admin reload emojis command")]
+    #[test_case("admin emoji_fallback" => Ok(Command::Admin(AdminCommand::EmojiFallbacks)) ; "test admin emoji fallback lists")]
+    #[test_case("admin emoji_fallback :sadparrot: 🦜" => Ok(Command::Admin(AdminCommand::SetEmojiFallback("sadparrot".into(), Some("🦜".into())))) ; "test admin emoji fallback unicode")]
+    #[test_case("admin emoji_fallback sadparrot :parrot:" => Ok(Command::Admin(AdminCommand::SetEmojiFallback("sadparrot".into(), Some("🦜".into())))) ; "test admin emoji fallback alias")]
+    #[test_case("admin emoji_fallback :sadparrot: none" => Ok(Command::Admin(AdminCommand::SetEmojiFallback("sadparrot".into(), None))) ; "test admin emoji fallback removed")]
+    #[test_case("admin emoji_fallback :sadparrot: :partyparrot:" => Err("admin emoji_fallback".into()) ; "test admin emoji fallback must be unicode")]
+    #[test_case("admin emoji_fallback :sadparrot:" => Err("admin emoji_fallback".into()) ; "test admin emoji fallback missing")]
+    #[test_case("test_send_home" => Err("unknown".into()) ; "test old testing commands are unknown")]
+    #[test_case("history" => Ok(Command::History(5)) ; "test history command default length")]
+    #[test_case("history 12" => Ok(Command::History(12)) ; "test history command with length")]
+    #[test_case("history 1000" => Ok(Command::History(50)) ; "test history command length is capped")]
+    #[test_case("history zero" => Err("history".into()) ; "test history invalid length gives usage")]
+    #[test_case("histroy" => Err("unknown, did you mean history".into()) ; "test history typo suggests history")]
+    #[test_case("reuse 2" => Ok(Command::Reuse(2)) ; "test reuse command")]
+    #[test_case("reuse 0" => Err("reuse".into()) ; "test reuse zero gives usage")]
+    #[test_case("reuse" => Err("reuse".into()) ; "test reuse empty gives usage")]
+    #[test_case("export" => Ok(Command::Export(ExportFormat::Json)) ; "test export defaults to json")]
+    #[test_case("export CSV" => Ok(Command::Export(ExportFormat::Csv)) ; "test export csv")]
+    #[test_case("export xml" => Err("export".into()) ; "test export unknown format gives usage")]
+    fn test_commmand_splitting(input: &str) -> std::result::Result<Command, String> {
+        init();
+        let bot = get_test_bot();
+        // Errors are compared by kind, their wording is tested in the parser module
+        bot.parse_cmd(input).map_err(|e| match e {
+            ParseError::Usage { command, .. } => command.into(),
+            ParseError::Unknown {
+                suggestion: Some(suggestion),
+                ..
+            } => format!("unknown, did you mean {suggestion}"),
+            ParseError::Unknown { .. } => "unknown".into(),
+            ParseError::UnterminatedQuote => "unterminated quote".into(),
+        })
+    }
+
+    /* Test Mention Stripping */
+    #[test_case("@**Status Bot** status :crab: Hacking" => "status :crab: Hacking" ; "test mention at start")]
+    #[test_case("@_**Status Bot** show" => "show" ; "test silent mention")]
+    #[test_case("@**Status Bot|1234** help" => "help" ; "test mention with user id")]
+    #[test_case("  show @**Status Bot**  " => "show" ; "test mention at end")]
+    #[test_case("@**Someone Else** show" => "@**Someone Else** show" ; "test mention of another user is kept")]
+    #[test_case("show" => "show" ; "test no mention")]
+    fn test_strip_mention(input: &str) -> String {
         init();
         let bot = get_test_bot();
-        bot.parse_cmd(input)
+        bot.strip_mention(input, "Status Bot")
     }
 
     /* Test Zulip Username Parsing */
@@ -861,11 +2716,47 @@ mod tests {
     #[test_case("", "Octopass is the best checkin project!", "<time:2023-11-29T20:00:00-05:00>" =>
         Status{ emoji: None, status: Some("Octopass is the best checkin project!".into()), expires_at: Some(datetime!(2023-11-29 20:00:00 -5)) }
         ; "test text and time")]
+    #[test_case("🦀", "", "<time:2023-11-29T20:00:00-05:00>" =>
+        Status{ emoji: Some(emojic::flat::CRAB.grapheme.into()), status: None, expires_at: Some(datetime!(2023-11-29 20:00:00 -5)) }
+        ; "test unicode emoji")]
+    #[test_case("👩🏽‍💻", "", "<time:2023-11-29T20:00:00-05:00>" =>
+        Status{ emoji: Some("👩🏽‍💻".into()), status: None, expires_at: Some(datetime!(2023-11-29 20:00:00 -5)) }
+        ; "test unicode emoji zwj sequence with skin tone")]
+    #[test_case(":red_apple:", "", "<time:2023-11-29T20:00:00-05:00>" =>
+        Status{ emoji: Some(emojic::flat::RED_APPLE.grapheme.into()), status: None, expires_at: Some(datetime!(2023-11-29 20:00:00 -5)) }
+        ; "test standard emoji alias")]
+    #[test_case("🦀", ":octopus: crabs and octopuses", "<time:2023-11-29T20:00:00-05:00>" =>
+        Status{ emoji: Some(emojic::flat::CRAB.grapheme.into()), status: Some(":octopus: crabs and octopuses".into()), expires_at: Some(datetime!(2023-11-29 20:00:00 -5)) }
+        ; "test alias after unicode emoji is text")]
     fn test_statuses(emoji: &str, status: &str, expires_at: &str) -> Status {
         init();
         let bot = get_test_bot();
         let input = format!("{emoji} {status} {expires_at}");
-        bot.parse_status(input.into())
+        bot.parse_status(input).0
+    }
+
+    /* Test Status Warnings */
+    #[test_case(":crab: Rewriting Status Bot" => Vec::<String>::new() ; "test no warnings")]
+    #[test_case(":crabb: Rewriting Status Bot" => vec!["emoji `:crabb:` not recognised, did you mean :crab:?".to_string()] ; "test unknown emoji with suggestion")]
+    #[test_case(":custom_zulip_emoji_like_sadparrot:" => vec!["emoji `:custom_zulip_emoji_like_sadparrot:` not recognised".to_string()] ; "test unknown emoji")]
+    #[test_case(":crab: Rewriting Status Bot <time:tomorrow>" => vec!["couldn't read time `tomorrow`, defaulted to 30m".to_string()] ; "test invalid time with text")]
+    #[test_case(":crab: <time:tomorrow>" => vec!["couldn't read time `tomorrow`, the status does not expire".to_string()] ; "test invalid time without text")]
+    fn test_status_warnings(input: &str) -> Vec<String> {
+        init();
+        let bot = get_test_bot();
+        let (_, warnings) = bot.parse_status(input.into());
+        warnings.iter().map(ToString::to_string).collect()
+    }
+
+    /* Test Emoji Parsing */
+    #[test_case("crab" => Ok("🦀".into()) ; "test zulip alias")]
+    #[test_case("crabb" => Err(vec![":crab:".into()]) ; "test typo suggests the alias")]
+    #[test_case("thermom" => Err(vec![":thermometer:".into(), ":face_with_thermometer:".into()]) ; "test several suggestions")]
+    #[test_case("custom_zulip_emoji_like_sadparrot" => Err(vec![]) ; "test nothing to suggest")]
+    fn test_parse_emoji(alias: &str) -> std::result::Result<String, Vec<String>> {
+        init();
+        let bot = get_test_bot();
+        bot.parse_emoji(alias)
     }
 
     /* Test Status Display */
@@ -884,4 +2775,142 @@ mod tests {
         // Render status as display
         format!("{status}")
     }
+
+    #[test_case(None => true ; "test never refreshed")]
+    #[test_case(Some(datetime!(2024-01-09 10:29:50 UTC)) => false ; "test refreshed recently")]
+    #[test_case(Some(datetime!(2024-01-09 10:29:00 UTC)) => true ; "test refreshed a minute ago")]
+    fn test_desks_are_stale(last_refresh: Option<OffsetDateTime>) -> bool {
+        super::desks_are_stale(last_refresh, datetime!(2024-01-09 10:30 UTC))
+    }
+
+    fn has_beacon(bot: &Bot, zulip_id: u64) -> bool {
+        let now = OffsetDateTime::now_utc();
+        bot.beacons
+            .read(|beacons| beacons.active(now).iter().any(|b| b.zulip_id == zulip_id))
+    }
+
+    #[tokio::test]
+    async fn test_invalid_pair_status_lights_no_beacon() {
+        init();
+        let bot = get_test_bot();
+        let sender = Sender {
+            id: 4241,
+            full_name: "Jacob Young".into(),
+        };
+        let position = Position { x: 1, y: 1 };
+        let reply = bot
+            .cmd_pair(&sender, 1, &position, "rust ".repeat(100), None)
+            .await
+            .unwrap();
+        assert!(matches!(reply, Reply::Content { content } if !content.contains("Updated")));
+        assert!(!has_beacon(bot, sender.id));
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_pair_lights_no_beacon() {
+        init();
+        let mut config = Config::load().expect("the test environment is a valid configuration");
+        config.dry_run = true;
+        let client = Client::builder().build::<_, hyper::Body>(HttpsConnector::new());
+        let bot = Bot::new(client, crate::emoji::bundled_table(), config);
+        let sender = Sender {
+            id: 4242,
+            full_name: "Jacob Young".into(),
+        };
+        let position = Position { x: 1, y: 1 };
+        let reply = bot
+            .cmd_pair(&sender, 1, &position, "rust".into(), None)
+            .await
+            .unwrap();
+        assert!(matches!(reply, Reply::Content { content } if content.contains("Preview")));
+        assert!(!has_beacon(&bot, sender.id));
+    }
+
+    fn admin_webhook(token: &str) -> OutgoingWebhook {
+        let mut webhook: OutgoingWebhook =
+            serde_json::from_str(include_str!("../webhook.json")).unwrap();
+        webhook.data = "admin stats".into();
+        webhook.token = token.into();
+        // A maintainer in the test environment
+        webhook.message.sender_id = 1;
+        webhook
+    }
+
+    #[tokio::test]
+    async fn test_forged_token_cannot_run_admin() {
+        init();
+        let bot = get_test_bot();
+        assert!(matches!(
+            bot.respond(admin_webhook("forged")).await,
+            Reply::ResponseNotRequired { .. }
+        ));
+        let token = std::env::var(ZULIP_BOT_API_TOKEN).unwrap();
+        assert!(matches!(
+            bot.respond(admin_webhook(&token)).await,
+            Reply::Content { content } if !content.starts_with("Sorry")
+        ));
+    }
+
+    fn owned_desk(id: usize, owner: &str) -> Desk {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "type": "Desk",
+            "pos": { "x": id, "y": 1 },
+            "color": "light-orange",
+            "emoji": null,
+            "status": null,
+            "profile_url": null,
+            "owner": { "id": id, "name": owner, "image_url": "" },
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_the_only_desk_of_a_name_cannot_be_claimed() {
+        init();
+        let config = Config::load().expect("the test environment is a valid configuration");
+        let client = Client::builder().build::<_, hyper::Body>(HttpsConnector::new());
+        let bot = Bot::new(client, crate::emoji::bundled_table(), config);
+        bot.store_desks(vec![owned_desk(4243, "Jacob Young")]);
+        let sender = Sender {
+            id: 4243,
+            full_name: "Jacob Young".into(),
+        };
+        let reply = bot.cmd_desk(&sender, 4243).await.unwrap();
+        assert!(matches!(reply, Reply::Content { content } if content.contains("only desk")));
+        assert_eq!(bot.desk_choices.read(|c| c.claimed_by(4243)), None);
+    }
+
+    /// Lookups running while the desks are refreshed always see a complete snapshot
+    #[test]
+    fn test_lookups_during_refreshes() {
+        init();
+        let config = Config::load().expect("the test environment is a valid configuration");
+        let client = Client::builder().build::<_, hyper::Body>(HttpsConnector::new());
+        let bot = Bot::new(client, crate::emoji::bundled_table(), config);
+        let desks: Vec<Desk> = (0..200)
+            .map(|id| owned_desk(id, &format!("Owner {id}")))
+            .collect();
+        bot.store_desks(desks.clone());
+
+        std::thread::scope(|scope| {
+            for _ in 0..2 {
+                scope.spawn(|| {
+                    for _ in 0..200 {
+                        bot.store_desks(desks.clone());
+                    }
+                });
+            }
+            for reader in 0..4 {
+                let bot = &bot;
+                scope.spawn(move || {
+                    for i in 0..250 {
+                        let id = (reader * 250 + i) % 200;
+                        let found = bot.find_desk(1, &format!("Owner {id} (they/them) (W2'24)"));
+                        assert!(matches!(found, DeskMatch::One(desk_id, _) if desk_id == id));
+                    }
+                });
+            }
+        });
+    }
 }
@@ -0,0 +1,138 @@
+//! The command line of the `statusbot` binary. Besides running the bot, operators can diagnose
+//! desks and the bot from a shell instead of sending Zulip DMs
+use crate::{bot::Bot, Result};
+
+pub const USAGE: &str = "Usage: statusbot [COMMAND]
+
+Commands:
+  serve                          Runs the bot, the default
+  check-config                   Checks the configuration and exits
+  desks                          Lists the owner of every desk in Virtual RC
+  lookup <zulip name>            Explains which desk a Zulip name is matched to
+  set-status <desk id> <status>  Sets the status of a desk, e.g. set-status 42 :crab: Pairing on Rust
+  send-home                      Sends the bot to its home position
+  help                           Shows this message";
+
+/// A command of the `statusbot` binary
+#[derive(Debug, PartialEq, Eq)]
+pub enum Cli {
+    Serve,
+    CheckConfig,
+    Desks,
+    Lookup(String),
+    SetStatus(usize, String),
+    SendHome,
+    Help,
+}
+
+impl Cli {
+    /// Parses the arguments following the name of the binary. Without arguments the bot is run
+    pub fn parse(args: &[String]) -> std::result::Result<Cli, String> {
+        let Some((command, rest)) = args.split_first() else {
+            return Ok(Cli::Serve);
+        };
+        let rest = rest.join(" ");
+        let rest = rest.trim();
+        let no_args = |cli: Cli| match rest.is_empty() {
+            true => Ok(cli),
+            false => Err(format!("`{command}` does not take arguments")),
+        };
+        match command.as_str() {
+            "serve" => no_args(Cli::Serve),
+            // The flag predates the subcommands
+            "check-config" | "--check-config" => no_args(Cli::CheckConfig),
+            "desks" => no_args(Cli::Desks),
+            "send-home" => no_args(Cli::SendHome),
+            "help" | "--help" | "-h" => Ok(Cli::Help),
+            "lookup" if rest.is_empty() => Err("`lookup` needs a Zulip name".into()),
+            "lookup" => Ok(Cli::Lookup(rest.into())),
+            "set-status" => {
+                let (desk_id, status) = rest.split_once(' ').unwrap_or((rest, ""));
+                let desk_id = desk_id
+                    .parse()
+                    .map_err(|_| format!("`{desk_id}` is not a desk id"))?;
+                match status.trim() {
+                    "" => Err("`set-status` needs a status".into()),
+                    status => Ok(Cli::SetStatus(desk_id, status.into())),
+                }
+            }
+            command => Err(format!("Unknown command `{command}`")),
+        }
+    }
+}
+
+/// Runs an operator command and returns what to print
+pub async fn run(command: Cli, bot: &Bot) -> Result<String> {
+    match command {
+        Cli::Desks => {
+            bot.cache_desk_owners().await?;
+            let lines: Vec<String> = bot
+                .owned_desks()
+                .into_iter()
+                .map(|(name, desk_id, pos)| format!("{desk_id}\t({}, {})\t{name}", pos.x, pos.y))
+                .collect();
+            Ok(format!("desk\tposition\towner\n{}", lines.join("\n")))
+        }
+        Cli::Lookup(zulip_name) => {
+            // The name is still parsed when the desks cannot be fetched
+            let fetched = bot.cache_desk_owners().await;
+            let lookup = bot.lookup(&zulip_name);
+            let desk = match (fetched, lookup.desks.as_slice()) {
+                (Err(e), _) => format!("unknown, failed to fetch the desks: {e}"),
+                (Ok(_), []) => "none".into(),
+                (Ok(_), desks) => desks
+                    .iter()
+                    .map(|(desk_id, pos)| format!("{desk_id} at ({}, {})", pos.x, pos.y))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            };
+            Ok(format!(
+                "Zulip name: {zulip_name}\nParsed name: {}\nVirtual RC name: {}\nDesk: {desk}",
+                lookup.parsed, lookup.name
+            ))
+        }
+        Cli::SetStatus(desk_id, status) => {
+            // Custom emojis are only known after asking Zulip
+            if let Err(e) = bot.cache_custom_emojis().await {
+                warn!("Failed to load the custom emojis of Zulip. Err = {e}");
+            }
+            bot.set_desk_status(desk_id, status).await
+        }
+        Cli::SendHome => {
+            bot.send_bot_home().await?;
+            Ok("Sent the bot home".into())
+        }
+        Cli::Serve | Cli::CheckConfig | Cli::Help => {
+            Err(format!("`{command:?}` is not an operator command").into())
+        }
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Tests                                   */
+/* -------------------------------------------------------------------------- */
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::Cli;
+
+    #[test_case(&[] => Ok(Cli::Serve) ; "test no arguments serve")]
+    #[test_case(&["serve"] => Ok(Cli::Serve) ; "test serve")]
+    #[test_case(&["--check-config"] => Ok(Cli::CheckConfig) ; "test check config flag")]
+    #[test_case(&["check-config"] => Ok(Cli::CheckConfig) ; "test check config")]
+    #[test_case(&["desks", "all"] => Err("`desks` does not take arguments".into()) ; "test unexpected argument")]
+    #[test_case(&["lookup", "Jacob Young (he/him) (W2'23)"] => Ok(Cli::Lookup("Jacob Young (he/him) (W2'23)".into())) ; "test lookup quoted")]
+    #[test_case(&["lookup", "Jacob", "Young"] => Ok(Cli::Lookup("Jacob Young".into())) ; "test lookup unquoted")]
+    #[test_case(&["lookup"] => Err("`lookup` needs a Zulip name".into()) ; "test lookup without name")]
+    #[test_case(&["set-status", "42", ":crab:", "Pairing", "on", "Rust"] => Ok(Cli::SetStatus(42, ":crab: Pairing on Rust".into())) ; "test set status")]
+    #[test_case(&["set-status", "desk", ":crab:"] => Err("`desk` is not a desk id".into()) ; "test set status invalid desk")]
+    #[test_case(&["set-status", "42"] => Err("`set-status` needs a status".into()) ; "test set status without status")]
+    #[test_case(&["send-home"] => Ok(Cli::SendHome) ; "test send home")]
+    #[test_case(&["-h"] => Ok(Cli::Help) ; "test help")]
+    #[test_case(&["sevre"] => Err("Unknown command `sevre`".into()) ; "test unknown command")]
+    fn test_parse(args: &[&str]) -> Result<Cli, String> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        Cli::parse(&args)
+    }
+}
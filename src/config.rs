@@ -0,0 +1,508 @@
+//! Every setting of Status Bot, read once at startup.
+//!
+//! Settings are read from environment variables (see `.env.example`). When CONFIG_FILE is set, it
+//! names a TOML file of the same variables, e.g. `server_port = 9090` or `zulip_bot_maintainers =
+//! [1, 2]`. Environment variables take precedence over the file so secrets can stay out of it
+use std::{
+    collections::HashMap,
+    env,
+    fmt::Display,
+    fs,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use url::Url;
+
+use crate::{
+    auth::Policy, consts::*, digest::DigestConfig, rc::Position, secret::Secret,
+    telemetry::OtlpConfig,
+};
+
+/// The validated configuration
+#[derive(Debug)]
+pub struct Config {
+    /// SERVER_DOMAIN:SERVER_PORT
+    pub address: SocketAddr,
+    pub data_dir: PathBuf,
+    pub dry_run: bool,
+    /// A path or URL to load the Zulip emoji table from
+    pub emoji_table: Option<String>,
+    pub history_retention_days: i64,
+    pub zulip: ZulipConfig,
+    pub rc: RcConfig,
+    pub policy: Policy,
+    /// An unset or empty allowlist means every stream is allowed
+    pub stream_allowlist: Option<Vec<String>>,
+    /// Disabled unless DIGEST_STREAM is set
+    pub digest: Option<DigestConfig>,
+    /// Disabled unless OTEL_EXPORTER_OTLP_ENDPOINT is set
+    pub otlp: Option<OtlpConfig>,
+}
+
+#[derive(Debug)]
+pub struct ZulipConfig {
+    pub site: Url,
+    pub bot_email: Secret,
+    pub api_key: Secret,
+    /// Sent by Zulip with every outgoing webhook
+    pub api_token: Secret,
+}
+
+#[derive(Debug)]
+pub struct RcConfig {
+    pub site: Url,
+    pub app_id: Secret,
+    pub app_secret: Secret,
+    pub bot_id: String,
+    /// Where the bot waits between status updates
+    pub home: Position,
+}
+
+/// Every problem found in the configuration
+#[derive(Debug, PartialEq, Eq)]
+pub struct ConfigError(pub Vec<String>);
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The configuration is invalid:")?;
+        for problem in &self.0 {
+            write!(f, "\n  - {problem}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Loads the configuration from CONFIG_FILE, if set, and the environment
+    pub fn load() -> Result<Config, ConfigError> {
+        let mut vars = HashMap::new();
+        let mut problems = Vec::new();
+        if let Ok(path) = env::var(CONFIG_FILE) {
+            match read_file(Path::new(&path)) {
+                Ok(file) => vars.extend(file),
+                Err(e) => problems.push(e),
+            }
+        }
+        overlay(&mut vars, env::vars());
+        match Config::from_vars(&vars) {
+            Ok(config) if problems.is_empty() => Ok(config),
+            Ok(_) => Err(ConfigError(problems)),
+            Err(ConfigError(more)) => {
+                problems.extend(more);
+                Err(ConfigError(problems))
+            }
+        }
+    }
+
+    /// Validates the variables, reporting every problem at once
+    pub fn from_vars(vars: &HashMap<String, String>) -> Result<Config, ConfigError> {
+        let mut vars = Vars {
+            vars,
+            problems: Vec::new(),
+        };
+
+        let domain = vars.required(SERVER_DOMAIN);
+        let port: Option<u16> = vars.required_parse(SERVER_PORT, "a port number");
+        let address = match (domain, port) {
+            (Some(domain), Some(port)) => match format!("{domain}:{port}").parse() {
+                Ok(address) => Some(address),
+                Err(_) => vars.problem(format!(
+                    "{SERVER_DOMAIN} must be an IP address to bind to, got `{domain}`"
+                )),
+            },
+            _ => None,
+        };
+
+        let zulip_site = vars.url(ZULIP_SITE);
+        let bot_email = vars.required(ZULIP_BOT_EMAIL);
+        let api_key = vars.required(ZULIP_BOT_API_KEY);
+        let api_token = vars.required(ZULIP_BOT_API_TOKEN);
+
+        let rc_site = vars.url(RC_SITE);
+        let app_id = vars.required(RC_APP_ID);
+        let app_secret = vars.required(RC_APP_SECRET);
+        let bot_id: Option<u64> = vars.required_parse(RC_BOT_ID, "a number");
+        let home_x = vars.required_parse(BOT_HOME_X, "a number");
+        let home_y = vars.required_parse(BOT_HOME_Y, "a number");
+        let home = match (home_x, home_y) {
+            (Some(x), Some(y)) if x > GRID_X_MAX || y > GRID_Y_MAX => vars.problem(format!(
+                "{BOT_HOME_X}/{BOT_HOME_Y} must be inside of the grid (0-{GRID_X_MAX}, 0-{GRID_Y_MAX}), got ({x}, {y})"
+            )),
+            (Some(x), Some(y)) => Some(Position { x, y }),
+            _ => None,
+        };
+
+        let maintainers = vars.required(ZULIP_BOT_MAINTAINERS);
+        let policy = maintainers.and_then(|maintainers| {
+            let faculty = vars.optional(ZULIP_BOT_FACULTY).unwrap_or_default();
+            let overrides = vars.optional(COMMAND_ROLES).unwrap_or_default();
+            match Policy::new(&maintainers, &faculty, &overrides) {
+                Ok(policy) => Some(policy),
+                Err(e) => vars.problem(format!("Invalid authorization policy: {e}")),
+            }
+        });
+
+        let stream_allowlist = vars
+            .list(ZULIP_STREAM_ALLOWLIST)
+            .filter(|streams| !streams.is_empty());
+        let digest = vars.digest();
+        let otlp = vars
+            .optional(OTEL_EXPORTER_OTLP_ENDPOINT)
+            .and_then(|endpoint| {
+                let service_name = vars.optional(OTEL_SERVICE_NAME);
+                let headers = vars
+                    .optional(OTEL_EXPORTER_OTLP_HEADERS)
+                    .unwrap_or_default();
+                match OtlpConfig::new(&endpoint, service_name, &headers) {
+                    Ok(otlp) => Some(otlp),
+                    Err(e) => vars.problem(e),
+                }
+            });
+        let history_retention_days =
+            match vars.optional_parse(HISTORY_RETENTION_DAYS, "a number of days") {
+                Some(days @ 1..=MAX_HISTORY_RETENTION_DAYS) => days,
+                Some(days) => vars
+                    .problem(format!(
+                        "{HISTORY_RETENTION_DAYS} must be between 1 and {MAX_HISTORY_RETENTION_DAYS} days, got `{days}`"
+                    ))
+                    .unwrap_or(DEFAULT_HISTORY_RETENTION_DAYS),
+                None => DEFAULT_HISTORY_RETENTION_DAYS,
+            };
+        let data_dir = vars
+            .optional(DATA_DIR)
+            .unwrap_or_else(|| DEFAULT_DATA_DIR.into());
+        let dry_run = vars
+            .optional(DRY_RUN)
+            .is_some_and(|v| matches!(v.to_lowercase().as_str(), "1" | "true" | "yes"));
+        let emoji_table = vars.optional(EMOJI_TABLE);
+
+        let (
+            Some(address),
+            Some(zulip_site),
+            Some(bot_email),
+            Some(api_key),
+            Some(api_token),
+            Some(rc_site),
+            Some(app_id),
+            Some(app_secret),
+            Some(bot_id),
+            Some(home),
+            Some(policy),
+        ) = (
+            address, zulip_site, bot_email, api_key, api_token, rc_site, app_id, app_secret,
+            bot_id, home, policy,
+        )
+        else {
+            return Err(ConfigError(vars.problems));
+        };
+        if !vars.problems.is_empty() {
+            return Err(ConfigError(vars.problems));
+        }
+        Ok(Config {
+            address,
+            data_dir: data_dir.into(),
+            dry_run,
+            emoji_table,
+            history_retention_days,
+            zulip: ZulipConfig {
+                site: zulip_site,
+                bot_email: bot_email.into(),
+                api_key: api_key.into(),
+                api_token: api_token.into(),
+            },
+            rc: RcConfig {
+                site: rc_site,
+                app_id: app_id.into(),
+                app_secret: app_secret.into(),
+                bot_id: bot_id.to_string(),
+                home,
+            },
+            policy,
+            stream_allowlist,
+            digest,
+            otlp,
+        })
+    }
+}
+
+/// Overlays the environment on the variables of the config file. Empty environment variables are
+/// skipped, `.env.example` lists every variable empty and would otherwise unset the file's values
+fn overlay(vars: &mut HashMap<String, String>, env: impl IntoIterator<Item = (String, String)>) {
+    vars.extend(
+        env.into_iter()
+            .filter(|(_, value)| !value.trim().is_empty()),
+    );
+}
+
+/// Reads the variables of a TOML config file. Keys are case insensitive, and lists are joined
+/// with commas like the environment variables they stand for
+fn read_file(path: &Path) -> Result<HashMap<String, String>, String> {
+    let file = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {CONFIG_FILE} {}: {e}", path.display()))?;
+    let table: toml::Table = file
+        .parse()
+        .map_err(|e| format!("{CONFIG_FILE} {} is not valid TOML: {e}", path.display()))?;
+    table
+        .into_iter()
+        .map(|(key, value)| {
+            let key = key.to_uppercase();
+            match to_var(value) {
+                Some(value) => Ok((key, value)),
+                None => Err(format!(
+                    "{key} in {CONFIG_FILE} must be a value or a list of values"
+                )),
+            }
+        })
+        .collect()
+}
+
+fn to_var(value: toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(s) => Some(s),
+        toml::Value::Integer(i) => Some(i.to_string()),
+        toml::Value::Float(f) => Some(f.to_string()),
+        toml::Value::Boolean(b) => Some(b.to_string()),
+        toml::Value::Array(values) => values
+            .into_iter()
+            .map(|value| match value {
+                toml::Value::Array(_) => None,
+                value => to_var(value),
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(|values| values.join(COMMA)),
+        toml::Value::Datetime(_) | toml::Value::Table(_) => None,
+    }
+}
+
+/// Reads variables and records every problem found
+struct Vars<'a> {
+    vars: &'a HashMap<String, String>,
+    problems: Vec<String>,
+}
+
+impl Vars<'_> {
+    /// Records the problem. Always None so it can end a lookup
+    fn problem<T>(&mut self, problem: impl Into<String>) -> Option<T> {
+        self.problems.push(problem.into());
+        None
+    }
+
+    /// A set variable. Empty values count as unset, like the `.env` files they come from
+    fn optional(&self, key: &str) -> Option<String> {
+        self.vars
+            .get(key)
+            .map(|value| value.trim())
+            .filter(|value| !value.is_empty())
+            .map(Into::into)
+    }
+
+    fn required(&mut self, key: &str) -> Option<String> {
+        match self.optional(key) {
+            Some(value) => Some(value),
+            None => self.problem(format!("{key} is missing")),
+        }
+    }
+
+    fn optional_parse<T: FromStr>(&mut self, key: &str, expected: &str) -> Option<T> {
+        let value = self.optional(key)?;
+        match value.parse() {
+            Ok(parsed) => Some(parsed),
+            Err(_) => self.problem(format!("{key} must be {expected}, got `{value}`")),
+        }
+    }
+
+    fn required_parse<T: FromStr>(&mut self, key: &str, expected: &str) -> Option<T> {
+        match self.optional(key) {
+            Some(_) => self.optional_parse(key, expected),
+            None => self.problem(format!("{key} is missing")),
+        }
+    }
+
+    fn url(&mut self, key: &str) -> Option<Url> {
+        let value = self.required(key)?;
+        match Url::parse(&value) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => Some(url),
+            _ => self.problem(format!("{key} must be an http(s) URL, got `{value}`")),
+        }
+    }
+
+    /// A comma separated list
+    fn list(&self, key: &str) -> Option<Vec<String>> {
+        let value = self.optional(key)?;
+        Some(
+            value
+                .split(COMMA)
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(Into::into)
+                .collect(),
+        )
+    }
+
+    fn digest(&mut self) -> Option<DigestConfig> {
+        let stream = self.optional(DIGEST_STREAM)?;
+        let topic = self
+            .optional(DIGEST_TOPIC)
+            .unwrap_or_else(|| DEFAULT_DIGEST_TOPIC.into());
+        let at = self
+            .optional(DIGEST_TIME)
+            .unwrap_or_else(|| DEFAULT_DIGEST_TIME.into());
+        let Some(at) = crate::digest::parse_time_of_day(&at) else {
+            return self.problem(format!(
+                "{DIGEST_TIME} must be formatted as HH:MM, got `{at}`"
+            ));
+        };
+        let keywords = self
+            .list(DIGEST_KEYWORDS)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|keyword| keyword.to_lowercase())
+            .collect();
+        Some(DigestConfig {
+            stream,
+            topic,
+            at,
+            keywords,
+        })
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Tests                                   */
+/* -------------------------------------------------------------------------- */
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use test_case::test_case;
+
+    use super::{overlay, read_file, Config, ConfigError};
+    use crate::rc::Position;
+
+    fn vars(overrides: &[(&str, &str)]) -> HashMap<String, String> {
+        let mut vars: HashMap<String, String> = [
+            ("SERVER_DOMAIN", "127.0.0.1"),
+            ("SERVER_PORT", "9090"),
+            ("ZULIP_SITE", "https://recurse.zulipchat.com"),
+            ("ZULIP_BOT_EMAIL", "status-bot@zulipchat.com"),
+            ("ZULIP_BOT_API_KEY", "config-test-api-key"),
+            ("ZULIP_BOT_API_TOKEN", "config-test-api-token"),
+            ("ZULIP_BOT_MAINTAINERS", "1,2"),
+            ("RC_SITE", "https://recurse.rctogether.com"),
+            ("RC_APP_ID", "config-test-app-id"),
+            ("RC_APP_SECRET", "config-test-app-secret"),
+            ("RC_BOT_ID", "1"),
+            ("RC_BOT_HOME_X", "10"),
+            ("RC_BOT_HOME_Y", "20"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.into(), value.into()))
+        .collect();
+        for (key, value) in overrides {
+            vars.insert(key.to_string(), value.to_string());
+        }
+        vars
+    }
+
+    #[test]
+    fn test_valid_config() {
+        let config = Config::from_vars(&vars(&[
+            ("ZULIP_STREAM_ALLOWLIST", "checkins, , 397 Bridge"),
+            ("DIGEST_STREAM", "checkins"),
+            ("DRY_RUN", "True"),
+        ]))
+        .unwrap();
+        assert_eq!(config.address, "127.0.0.1:9090".parse().unwrap());
+        assert_eq!(config.rc.home, Position { x: 10, y: 20 });
+        assert_eq!(
+            config.stream_allowlist,
+            Some(vec!["checkins".into(), "397 Bridge".into()])
+        );
+        assert_eq!(
+            config.digest.map(|d| d.topic),
+            Some("daily statuses".into())
+        );
+        assert!(config.dry_run);
+        assert!(config.otlp.is_none());
+    }
+
+    #[test_case(&[("SERVER_PORT", "")] => vec!["SERVER_PORT is missing"] ; "test missing variable")]
+    #[test_case(&[("SERVER_PORT", "90000")] => vec!["SERVER_PORT must be a port number, got `90000`"] ; "test invalid port")]
+    #[test_case(&[("SERVER_DOMAIN", "localhost")] => vec!["SERVER_DOMAIN must be an IP address to bind to, got `localhost`"] ; "test bind address")]
+    #[test_case(&[("RC_SITE", "recurse.rctogether.com")] => vec!["RC_SITE must be an http(s) URL, got `recurse.rctogether.com`"] ; "test url without scheme")]
+    #[test_case(&[("RC_BOT_HOME_X", "ten")] => vec!["RC_BOT_HOME_X must be a number, got `ten`"] ; "test home is not a number")]
+    #[test_case(&[("RC_BOT_HOME_Y", "500")] => vec!["RC_BOT_HOME_X/RC_BOT_HOME_Y must be inside of the grid (0-169, 0-109), got (10, 500)"] ; "test home outside of the grid")]
+    #[test_case(&[("ZULIP_BOT_MAINTAINERS", "1,jacob")] => vec!["Invalid authorization policy: 'jacob' is not a Zulip user id"] ; "test invalid maintainer")]
+    #[test_case(&[("ZULIP_BOT_MAINTAINERS", " ")] => vec!["ZULIP_BOT_MAINTAINERS is missing"] ; "test no maintainers")]
+    #[test_case(&[("DIGEST_STREAM", "checkins"), ("DIGEST_TIME", "noon")] => vec!["DIGEST_TIME must be formatted as HH:MM, got `noon`"] ; "test invalid digest time")]
+    #[test_case(&[("OTEL_EXPORTER_OTLP_ENDPOINT", "http://localhost:4318"), ("OTEL_EXPORTER_OTLP_HEADERS", "key")]
+        => vec!["OTEL_EXPORTER_OTLP_HEADERS must be key=value pairs, got `key`"] ; "test invalid otlp headers")]
+    #[test_case(&[("HISTORY_RETENTION_DAYS", "0")] => vec!["HISTORY_RETENTION_DAYS must be between 1 and 3650 days, got `0`"] ; "test no history retention")]
+    #[test_case(&[("HISTORY_RETENTION_DAYS", "-7")] => vec!["HISTORY_RETENTION_DAYS must be between 1 and 3650 days, got `-7`"] ; "test negative history retention")]
+    #[test_case(&[("HISTORY_RETENTION_DAYS", "9223372036854775807")] => vec!["HISTORY_RETENTION_DAYS must be between 1 and 3650 days, got `9223372036854775807`"] ; "test huge history retention")]
+    #[test_case(&[("HISTORY_RETENTION_DAYS", "3650")] => Vec::<String>::new() ; "test longest history retention")]
+    #[test_case(&[("SERVER_PORT", "http"), ("ZULIP_SITE", ""), ("RC_BOT_ID", "bot"), ("HISTORY_RETENTION_DAYS", "forever")]
+        => vec![
+            "SERVER_PORT must be a port number, got `http`",
+            "ZULIP_SITE is missing",
+            "RC_BOT_ID must be a number, got `bot`",
+            "HISTORY_RETENTION_DAYS must be a number of days, got `forever`",
+        ] ; "test every problem is reported")]
+    fn test_invalid_config(overrides: &[(&str, &str)]) -> Vec<String> {
+        match Config::from_vars(&vars(overrides)) {
+            Ok(_) => vec![],
+            Err(ConfigError(problems)) => problems,
+        }
+    }
+
+    #[test]
+    fn test_read_file() {
+        let path =
+            std::env::temp_dir().join(format!("statusbot-config-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "server_port = 9090\nZULIP_BOT_MAINTAINERS = [1, 2]\ndry_run = true\n",
+        )
+        .unwrap();
+        let file = read_file(&path);
+        std::fs::remove_file(&path).unwrap();
+        let file = file.unwrap();
+        assert_eq!(file.get("SERVER_PORT").map(String::as_str), Some("9090"));
+        assert_eq!(
+            file.get("ZULIP_BOT_MAINTAINERS").map(String::as_str),
+            Some("1,2")
+        );
+        assert_eq!(file.get("DRY_RUN").map(String::as_str), Some("true"));
+    }
+
+    #[test]
+    fn test_empty_env_does_not_override_file() {
+        let mut merged = vars(&[("DATA_DIR", "/data"), ("DRY_RUN", "true")]);
+        let env = [("DATA_DIR", ""), ("DRY_RUN", "false"), ("SERVER_PORT", " ")];
+        overlay(
+            &mut merged,
+            env.map(|(key, value)| (key.to_string(), value.to_string())),
+        );
+        assert_eq!(merged.get("DATA_DIR").map(String::as_str), Some("/data"));
+        assert_eq!(merged.get("DRY_RUN").map(String::as_str), Some("false"));
+        let config = Config::from_vars(&merged).unwrap();
+        assert_eq!(config.data_dir, std::path::PathBuf::from("/data"));
+        assert_eq!(config.address.port(), 9090);
+    }
+
+    #[test]
+    fn test_config_error_display() {
+        let error = ConfigError(vec![
+            "SERVER_PORT is missing".into(),
+            "RC_SITE is missing".into(),
+        ]);
+        assert_eq!(
+            error.to_string(),
+            "The configuration is invalid:\n  - SERVER_PORT is missing\n  - RC_SITE is missing"
+        );
+    }
+}
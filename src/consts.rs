@@ -2,15 +2,57 @@
 pub const ENV_DEVEL: &str = ".env.devel";
 pub const ENV_PROD: &str = ".env.prod";
 pub const RUN_MODE: &str = "RUN_MODE";
+pub const CONFIG_FILE: &str = "CONFIG_FILE";
 pub const FLY_APP_NAME: &str = "FLY_APP_NAME";
 pub const PROD: &str = "PROD";
 pub const DEVEL: &str = "DEVEL";
 pub const SERVER_DOMAIN: &str = "SERVER_DOMAIN";
 pub const SERVER_PORT: &str = "SERVER_PORT";
+pub const LOG_FORMAT: &str = "LOG_FORMAT";
+pub const RUST_LOG: &str = "RUST_LOG";
+pub const OTEL_EXPORTER_OTLP_ENDPOINT: &str = "OTEL_EXPORTER_OTLP_ENDPOINT";
+pub const OTEL_EXPORTER_OTLP_HEADERS: &str = "OTEL_EXPORTER_OTLP_HEADERS";
+pub const OTEL_SERVICE_NAME: &str = "OTEL_SERVICE_NAME";
 pub const DESKS_INTERVAL: u64 = 1 * 60; /* 1 minutes */
+/// Custom emojis rarely change, `admin reload_emojis` loads them right away
+pub const CUSTOM_EMOJIS_INTERVAL: u64 = 15 * 60; /* 15 minutes */
+/// A desk lookup that finds nothing refreshes the desks when they are older than this, so new desk
+/// owners do not wait for the next DESKS_INTERVAL
+pub const DESKS_STALE_AFTER: u64 = 15; /* 15 seconds */
+/// How long the server waits for the first refresh of the desks before serving anyway
+pub const DESKS_WARMUP_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
 pub const NOTFOUND: &str = "NOT FOUND";
 pub const ROOT: &str = "/";
 pub const STATUS_ENDPOINT: &str = "/status";
+pub const READY_ENDPOINT: &str = "/ready";
+/// How long in-flight commands may take to finish on shutdown
+pub const DRAIN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(20);
+
+/* Store */
+pub const DATA_DIR: &str = "DATA_DIR";
+pub const DEFAULT_DATA_DIR: &str = "data";
+pub const GROUPS_FILE: &str = "groups.json";
+pub const DIGEST_OPT_INS_FILE: &str = "digest_opt_ins.json";
+pub const BEACONS_FILE: &str = "beacons.json";
+pub const HISTORY_FILE: &str = "history.jsonl";
+pub const DELEGATIONS_FILE: &str = "delegations.json";
+pub const DESK_CHOICES_FILE: &str = "desk_choices.json";
+pub const EMOJI_FALLBACKS_FILE: &str = "emoji_fallbacks.json";
+
+/* History */
+pub const HISTORY_RETENTION_DAYS: &str = "HISTORY_RETENTION_DAYS";
+pub const DEFAULT_HISTORY_RETENTION_DAYS: i64 = 30;
+pub const MAX_HISTORY_RETENTION_DAYS: i64 = 3650;
+pub const DEFAULT_HISTORY_LENGTH: usize = 5;
+pub const MAX_HISTORY_LENGTH: usize = 50;
+
+/* Digest */
+pub const DIGEST_STREAM: &str = "DIGEST_STREAM";
+pub const DIGEST_TOPIC: &str = "DIGEST_TOPIC";
+pub const DIGEST_TIME: &str = "DIGEST_TIME";
+pub const DIGEST_KEYWORDS: &str = "DIGEST_KEYWORDS";
+pub const DEFAULT_DIGEST_TOPIC: &str = "daily statuses";
+pub const DEFAULT_DIGEST_TIME: &str = "13:00"; /* 9am in New York (EDT) */
 
 /* RC */
 pub const RC_SITE: &str = "RC_SITE";
@@ -29,13 +71,26 @@ pub const AUTHORIZATION: &str = "Authorization";
 pub const API_DESKS: &str = "/api/desks";
 pub const API_BOTS: &str = "/api/bots";
 
+/* Zulip */
+pub const API_MESSAGES: &str = "/api/v1/messages";
+pub const API_REALM_EMOJI: &str = "/api/v1/realm/emoji";
+pub const API_USERS: &str = "/api/v1/users";
+/// The longest message Zulip accepts, in characters
+pub const MAX_MESSAGE_LENGTH: usize = 10_000;
+
 /* Bot */
 pub const ZULIP_BOT_EMAIL: &str = "ZULIP_BOT_EMAIL";
 pub const ZULIP_BOT_API_KEY: &str = "ZULIP_BOT_API_KEY";
 pub const ZULIP_BOT_API_TOKEN: &str = "ZULIP_BOT_API_TOKEN";
 pub const ZULIP_SITE: &str = "ZULIP_SITE";
+pub const ZULIP_STREAM_ALLOWLIST: &str = "ZULIP_STREAM_ALLOWLIST";
 pub const BOT_HOME_X: &str = "RC_BOT_HOME_X";
 pub const BOT_HOME_Y: &str = "RC_BOT_HOME_Y";
+pub const ZULIP_BOT_MAINTAINERS: &str = "ZULIP_BOT_MAINTAINERS";
+pub const ZULIP_BOT_FACULTY: &str = "ZULIP_BOT_FACULTY";
+pub const COMMAND_ROLES: &str = "COMMAND_ROLES";
+pub const EMOJI_TABLE: &str = "EMOJI_TABLE";
+pub const DRY_RUN: &str = "DRY_RUN";
 
 pub const SPACE: &str = " ";
 pub const COMMA: &str = ",";
@@ -45,6 +100,9 @@ pub const RE_RC_PRONOUNS: &str = r"(?<pronouns>\([a-z/]+\))?";
 pub const RE_RC_BATCH: &str = r"(\((W|SP|Sp|S|F|m)\d?'\d{2}\))?";
 pub const RE_RC_NAME_PARTS: &str = r".*?";
 
+// A Zulip mention at the start of a message, e.g. @**Jacob Young** or @_**Jacob Young|123**
+pub const RE_ZULIP_MENTION: &str = r"^@_?\*\*(?<name>[^*|]+)(\|(?<id>\d+))?\*\*";
+
 // Combined = (:(.+?):)?([^<>\r\n\t]+)?(<time:(.+?)>)?
 pub const RE_EMOJI: &str = "(:(?<emoji>.+?):)?";
 pub const RE_STATUS: &str = "(?<status>[^<>\r\n\t]+)?";
@@ -61,18 +119,12 @@ pub const MISSING_DESK: &str = r#"**Unable to a find a desk in Vritual RC asssoc
 // Missing text:
 // * `clear` Clear your status
 // * `feedback {text}` Provide anonymous feedback to the Status Bot maintainer(s)
-pub const HELP_TEXT: &str = r#"**How to use Status Bot**:
-* `status {emoji} {text} {expires_at}` Set your status
-  * `{emoji}` (optional) - A unicode emoji
-    * Custom emojis are not supported (:sadparrot:)
-  * `{status}` (optional) - Status message for others to see
-    * Cannot contain `<` or `>` characters
-  * `{expires_at}` optional - The expiration time for the status (default 30m)
-    * Expiration should be set using zulip's [<time> selector](https://zulip.com/help/global-times)
-    * Choose a time in the future!
-  * `status :crab: Rewriting Status Bot in Rust <time:2025-01-01T10:00:00-04:00>`
-* `show` Display your current status
-* `help` Print help message
+pub const HELP_HEADER: &str = r#"**How to use Status Bot** (send `help {command}` to learn more about a command):
+"#;
+
+pub const HELP_FOOTER: &str = r#"
+You can also mention `@Status Bot` in a stream with any command. Status Bot will reply to you
+privately so the topic stays clean.
 
 Note: Status Bot uses your Zulip username to match your Virtual RC username. If you're having
 trouble setting your status you can tell Status Bot what your Virtual RC name is with the
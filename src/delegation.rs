@@ -0,0 +1,205 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{consts::*, zulip::Member};
+
+/// A Zulip user named in a command, with their user id when Zulip included it in the mention
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Mention {
+    pub name: String,
+    pub id: Option<u64>,
+}
+
+impl Mention {
+    /// Parses a mention at the start of the input, returning it and the rest of the input
+    pub fn parse(input: &str) -> Option<(Mention, &str)> {
+        let input = input.trim_start();
+        let re_mention = Regex::new(RE_ZULIP_MENTION).unwrap();
+        let captures = re_mention.captures(input)?;
+        let mention = Mention {
+            name: captures["name"].trim().into(),
+            id: captures.name("id").and_then(|id| id.as_str().parse().ok()),
+        };
+        let rest = &input[captures[0].len()..];
+        Some((mention, rest.trim()))
+    }
+
+    /// Parses a mention, or falls back to treating the whole input as a plain Zulip name
+    pub fn parse_or_name(input: &str) -> Option<Mention> {
+        match Mention::parse(input) {
+            Some((mention, _)) => Some(mention),
+            None if input.trim().is_empty() => None,
+            None => Some(Mention {
+                name: input.trim().trim_start_matches('@').into(),
+                id: None,
+            }),
+        }
+    }
+
+    /// Fills in the Zulip user id of a mention without one, from the users of the realm. Fails when
+    /// no user or more than one user has the name
+    pub fn resolve(self, members: &[Member]) -> Result<Mention, String> {
+        if self.id.is_some() {
+            return Ok(self);
+        }
+        let mut named = members
+            .iter()
+            .filter(|member| member.full_name.eq_ignore_ascii_case(&self.name));
+        match (named.next(), named.next()) {
+            (Some(member), None) => Ok(Mention {
+                name: member.full_name.clone(),
+                id: Some(member.user_id),
+            }),
+            (None, _) => Err(format!("There is no Zulip user named **{}**", self.name)),
+            (Some(_), Some(_)) => Err(format!(
+                "Several Zulip users are named **{}**, pick them from the list Zulip shows after typing `@` so the mention includes their id",
+                self.name
+            )),
+        }
+    }
+
+    /// Whether this mention names the given Zulip user. Only ids are compared, anyone can change
+    /// their display name
+    pub fn is(&self, zulip_id: u64) -> bool {
+        self.id == Some(zulip_id)
+    }
+}
+
+/// Permission, given by the owner of a desk, for a delegate to set the status of that desk
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Delegation {
+    /// The Zulip user id of the desk owner
+    pub owner_id: u64,
+    /// The Zulip full name of the desk owner
+    pub owner_name: String,
+    pub delegate: Mention,
+}
+
+/// Every delegation made with the `delegate` command
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(transparent)]
+pub struct Delegations(pub Vec<Delegation>);
+
+impl Delegations {
+    /// Adds a delegation. Returns false if the delegate could already set the owner's status
+    ///
+    /// The delegate must be resolved to a Zulip user id, see [`Mention::resolve`]
+    pub fn grant(&mut self, delegation: Delegation) -> bool {
+        let exists = self
+            .0
+            .iter()
+            .any(|d| d.owner_id == delegation.owner_id && d.delegate.id == delegation.delegate.id);
+        if !exists {
+            self.0.push(delegation);
+        }
+        !exists
+    }
+
+    /// Removes the delegations of an owner to a delegate. Returns false if there were none
+    pub fn revoke(&mut self, owner_id: u64, delegate_id: u64) -> bool {
+        let before = self.0.len();
+        self.0
+            .retain(|d| d.owner_id != owner_id || !d.delegate.is(delegate_id));
+        self.0.len() != before
+    }
+
+    /// The delegation allowing a Zulip user to set the status of the owner
+    pub fn find(&self, owner_id: u64, delegate_id: u64) -> Option<&Delegation> {
+        self.0
+            .iter()
+            .find(|d| d.owner_id == owner_id && d.delegate.is(delegate_id))
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Tests                                   */
+/* -------------------------------------------------------------------------- */
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::{Delegation, Delegations, Mention};
+    use crate::zulip::Member;
+
+    fn mention(name: &str, id: Option<u64>) -> Mention {
+        Mention {
+            name: name.into(),
+            id,
+        }
+    }
+
+    #[test_case("@**Jacob Young** out sick" => Some((mention("Jacob Young", None), "out sick".into())) ; "test mention")]
+    #[test_case("@_**Jacob Young|123** :sick: out sick" => Some((mention("Jacob Young", Some(123)), ":sick: out sick".into())) ; "test silent mention with id")]
+    #[test_case("@**Jacob Young (he/him) (F2'23)**" => Some((mention("Jacob Young (he/him) (F2'23)", None), "".into())) ; "test mention with pronouns and batch")]
+    #[test_case("Jacob Young out sick" => None ; "test plain name is not a mention")]
+    #[test_case("out sick @**Jacob Young**" => None ; "test mention must come first")]
+    fn test_parse_mention(input: &str) -> Option<(Mention, String)> {
+        Mention::parse(input).map(|(mention, rest)| (mention, rest.into()))
+    }
+
+    #[test_case("@**Jacob Young**" => Some(mention("Jacob Young", None)) ; "test mention")]
+    #[test_case("@Jacob Young" => Some(mention("Jacob Young", None)) ; "test plain name with at")]
+    #[test_case("  " => None ; "test empty")]
+    fn test_parse_or_name(input: &str) -> Option<Mention> {
+        Mention::parse_or_name(input)
+    }
+
+    fn members() -> Vec<Member> {
+        [
+            (1, "Jacob Young"),
+            (2, "Jane Doe"),
+            (3, "John Doe"),
+            (4, "John Doe"),
+        ]
+        .into_iter()
+        .map(|(user_id, full_name)| Member {
+            user_id,
+            full_name: full_name.into(),
+            is_active: true,
+        })
+        .collect()
+    }
+
+    #[test_case(mention("Jane", Some(2)) => Ok(mention("Jane", Some(2))) ; "test mention with id")]
+    #[test_case(mention("jane doe", None) => Ok(mention("Jane Doe", Some(2))) ; "test name ignoring case")]
+    #[test_case(mention("Jane Smith", None) => Err("There is no Zulip user named **Jane Smith**".into()) ; "test unknown name")]
+    #[test_case(mention("John Doe", None) => matches Err(_) ; "test ambiguous name")]
+    fn test_resolve(mention: Mention) -> Result<Mention, String> {
+        mention.resolve(&members())
+    }
+
+    #[test_case(1, 2 => true ; "test delegate")]
+    #[test_case(9, 2 => false ; "test owner does not match")]
+    #[test_case(1, 3 => false ; "test someone else is not a delegate")]
+    fn test_find(owner_id: u64, delegate_id: u64) -> bool {
+        let mut delegations = Delegations::default();
+        delegations.grant(Delegation {
+            owner_id: 1,
+            owner_name: "Jacob Young".into(),
+            delegate: mention("Jane Doe", Some(2)),
+        });
+        // A delegation stored without an id never matches, even by name
+        delegations.grant(Delegation {
+            owner_id: 1,
+            owner_name: "Jacob Young".into(),
+            delegate: mention("John Doe", None),
+        });
+        delegations.find(owner_id, delegate_id).is_some()
+    }
+
+    #[test_case(1, 2 => (true, 0) ; "test revoke by id")]
+    #[test_case(2, 2 => (false, 1) ; "test only the owner can revoke")]
+    #[test_case(1, 3 => (false, 1) ; "test revoke someone else")]
+    fn test_grant_and_revoke(owner_id: u64, delegate_id: u64) -> (bool, usize) {
+        let mut delegations = Delegations::default();
+        let delegation = Delegation {
+            owner_id: 1,
+            owner_name: "Jacob Young".into(),
+            delegate: mention("Jane Doe", Some(2)),
+        };
+        assert!(delegations.grant(delegation.clone()));
+        assert!(!delegations.grant(delegation));
+        let revoked = delegations.revoke(owner_id, delegate_id);
+        (revoked, delegations.0.len())
+    }
+}
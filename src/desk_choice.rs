@@ -0,0 +1,161 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rc::Position;
+
+/// The desk each Zulip user chose with the `desk` command, when their Virtual RC name owns several
+/// desks (people sharing a name, or someone owning more than one desk)
+///
+/// [Zulip user id] -> [Virtual RC Desk.ID]
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(transparent)]
+pub struct DeskChoices(pub BTreeMap<u64, usize>);
+
+/// The desks matching a Zulip user
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeskMatch {
+    None,
+    One(usize, Position),
+    /// Several desks match and the user has not chosen one of them yet
+    Ambiguous(Vec<(usize, Position)>),
+}
+
+impl DeskChoices {
+    /// Picks the desk of the user among the desks owned by their name. Desks chosen by someone else
+    /// are left out, and a choice for a desk the name no longer owns is ignored
+    pub fn pick(&self, zulip_id: u64, candidates: &[(usize, Position)]) -> DeskMatch {
+        let candidates: Vec<(usize, Position)> = candidates
+            .iter()
+            .filter(|(desk_id, _)| self.claimed_by(*desk_id).is_none_or(|id| id == zulip_id))
+            .cloned()
+            .collect();
+        match candidates.as_slice() {
+            [] => DeskMatch::None,
+            [(desk_id, pos)] => DeskMatch::One(*desk_id, pos.clone()),
+            _ => match self
+                .0
+                .get(&zulip_id)
+                .and_then(|chosen| candidates.iter().find(|(desk_id, _)| desk_id == chosen))
+            {
+                Some((desk_id, pos)) => DeskMatch::One(*desk_id, pos.clone()),
+                None => DeskMatch::Ambiguous(candidates),
+            },
+        }
+    }
+
+    /// Who chose the desk, if anyone did
+    pub fn claimed_by(&self, desk_id: usize) -> Option<u64> {
+        self.0
+            .iter()
+            .find(|(_, chosen)| **chosen == desk_id)
+            .map(|(zulip_id, _)| *zulip_id)
+    }
+
+    /// Records the choice of the user. A desk belongs to one person only, so a desk someone else
+    /// chose is refused and the Zulip id of that person is returned
+    pub fn claim(&mut self, zulip_id: u64, desk_id: usize) -> Result<(), u64> {
+        match self.claimed_by(desk_id) {
+            Some(other) if other != zulip_id => Err(other),
+            _ => {
+                self.0.insert(zulip_id, desk_id);
+                Ok(())
+            }
+        }
+    }
+
+    /// Forgets who chose the desk, returning their Zulip id
+    pub fn release(&mut self, desk_id: usize) -> Option<u64> {
+        let zulip_id = self.claimed_by(desk_id)?;
+        self.0.remove(&zulip_id);
+        Some(zulip_id)
+    }
+}
+
+/// Asks the user to choose between the desks owned by their name
+pub fn render_ambiguous(candidates: &[(usize, Position)]) -> String {
+    let desks: Vec<String> = candidates
+        .iter()
+        .map(|(desk_id, pos)| format!("* desk `{desk_id}` at ({}, {})", pos.x, pos.y))
+        .collect();
+    format!(
+        "Your Virtual RC name owns several desks:\n{}\nTell Status Bot which one is yours with `desk {{desk id}}`, e.g. `desk {}`",
+        desks.join("\n"),
+        candidates.first().map_or(0, |(desk_id, _)| *desk_id)
+    )
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Tests                                   */
+/* -------------------------------------------------------------------------- */
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::{DeskChoices, DeskMatch};
+    use crate::rc::Position;
+
+    fn desks(ids: &[usize]) -> Vec<(usize, Position)> {
+        ids.iter()
+            .map(|id| (*id, Position { x: *id, y: 1 }))
+            .collect()
+    }
+
+    #[test_case(&[], None => DeskMatch::None ; "test no desk")]
+    #[test_case(&[12], None => DeskMatch::One(12, Position { x: 12, y: 1 }) ; "test single desk")]
+    #[test_case(&[12, 40], None => DeskMatch::Ambiguous(desks(&[12, 40])) ; "test several desks without choice")]
+    #[test_case(&[12, 40], Some(40) => DeskMatch::One(40, Position { x: 40, y: 1 }) ; "test several desks with choice")]
+    #[test_case(&[12, 40], Some(7) => DeskMatch::Ambiguous(desks(&[12, 40])) ; "test choice no longer owned")]
+    fn test_pick(candidates: &[usize], chosen: Option<usize>) -> DeskMatch {
+        let mut choices = DeskChoices::default();
+        if let Some(chosen) = chosen {
+            choices.0.insert(1, chosen);
+        }
+        choices.pick(1, &desks(candidates))
+    }
+
+    #[test]
+    fn test_render_ambiguous() {
+        assert_eq!(
+            super::render_ambiguous(&desks(&[12, 40])),
+            "Your Virtual RC name owns several desks:\n* desk `12` at (12, 1)\n* desk `40` at (40, 1)\nTell Status Bot which one is yours with `desk {desk id}`, e.g. `desk 12`"
+        );
+    }
+
+    #[test]
+    fn test_claim_conflict() {
+        let mut choices = DeskChoices::default();
+        assert_eq!(choices.claim(1, 12), Ok(()));
+        assert_eq!(choices.claim(2, 12), Err(1));
+        // Choosing again, or another desk, is fine
+        assert_eq!(choices.claim(1, 12), Ok(()));
+        assert_eq!(choices.claim(2, 40), Ok(()));
+        assert_eq!(
+            choices.pick(2, &desks(&[12, 40])),
+            DeskMatch::One(40, Position { x: 40, y: 1 })
+        );
+    }
+
+    #[test]
+    fn test_release() {
+        let mut choices = DeskChoices::default();
+        choices.claim(1, 12).unwrap();
+        assert_eq!(choices.release(12), Some(1));
+        assert_eq!(choices.release(12), None);
+        assert_eq!(choices.claim(2, 12), Ok(()));
+    }
+
+    #[test]
+    fn test_desks_chosen_by_others_are_left_out() {
+        let mut choices = DeskChoices::default();
+        choices.claim(1, 12).unwrap();
+        assert_eq!(
+            choices.pick(2, &desks(&[12, 40])),
+            DeskMatch::One(40, Position { x: 40, y: 1 })
+        );
+        assert_eq!(
+            choices.pick(2, &desks(&[12, 40, 41])),
+            DeskMatch::Ambiguous(desks(&[40, 41]))
+        );
+    }
+}
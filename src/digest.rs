@@ -0,0 +1,75 @@
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+use time::{Duration, OffsetDateTime, Time};
+
+/// Where and when the daily digest of statuses is posted. The digest is opt-in for the deployment
+/// as well, see [`crate::config::Config::digest`]
+#[derive(Debug)]
+pub struct DigestConfig {
+    /// The stream the digest is posted to
+    pub stream: String,
+    /// The topic inside of the stream
+    pub topic: String,
+    /// The time of day (UTC) to post the digest
+    pub at: Time,
+    /// Only statuses containing one of these words are included. Empty includes every status
+    pub keywords: Vec<String>,
+}
+
+impl DigestConfig {
+    /// Whether a status text should be part of the digest
+    pub fn matches(&self, status: &str) -> bool {
+        let status = status.to_lowercase();
+        self.keywords.is_empty() || self.keywords.iter().any(|k| status.contains(k))
+    }
+}
+
+/// The desks (by Virtual RC desk id) whose owners opted in to the daily digest
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(transparent)]
+pub struct OptIns(pub BTreeSet<usize>);
+
+/// Parses a 24 hour `HH:MM` time of day
+pub fn parse_time_of_day(input: &str) -> Option<Time> {
+    let (hour, minute) = input.trim().split_once(':')?;
+    Time::from_hms(hour.parse().ok()?, minute.parse().ok()?, 0).ok()
+}
+
+/// The next moment after `now` at which the time of day `at` occurs
+pub fn next_run(now: OffsetDateTime, at: Time) -> OffsetDateTime {
+    let today = now.replace_time(at);
+    if today > now {
+        today
+    } else {
+        today + Duration::DAY
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Tests                                   */
+/* -------------------------------------------------------------------------- */
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+    use time::macros::{datetime, time};
+    use time::{OffsetDateTime, Time};
+
+    use super::{next_run, parse_time_of_day};
+
+    #[test_case("13:00" => Some(time!(13:00)) ; "test afternoon")]
+    #[test_case(" 9:05 " => Some(time!(09:05)) ; "test single digit hour with whitespace")]
+    #[test_case("24:00" => None ; "test hour out of range")]
+    #[test_case("1300" => None ; "test missing colon")]
+    #[test_case("" => None ; "test empty time")]
+    fn test_parse_time_of_day(input: &str) -> Option<Time> {
+        parse_time_of_day(input)
+    }
+
+    #[test_case(datetime!(2023-11-29 08:00:00 UTC), time!(13:00) => datetime!(2023-11-29 13:00:00 UTC) ; "test later today")]
+    #[test_case(datetime!(2023-11-29 13:00:00 UTC), time!(13:00) => datetime!(2023-11-30 13:00:00 UTC) ; "test exactly now is tomorrow")]
+    #[test_case(datetime!(2023-12-31 20:00:00 UTC), time!(13:00) => datetime!(2024-01-01 13:00:00 UTC) ; "test tomorrow across new year")]
+    fn test_next_run(now: OffsetDateTime, at: Time) -> OffsetDateTime {
+        next_run(now, at)
+    }
+}
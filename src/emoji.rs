@@ -0,0 +1,316 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use hyper::{Method, Request, StatusCode};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{consts::*, zulip::ZulipEmoji, HttpsClient, Result};
+
+/// The emoji table compiled into the binary, used when EMOJI_TABLE is not set
+const BUNDLED_TABLE: &str = include_str!("zulip.json");
+
+/// Emoji presentation selector, often left out by keyboards (❤ instead of ❤️)
+const VARIATION_SELECTOR_16: char = '\u{FE0F}';
+
+/// Every emoji grapheme known to emojic, including skin tone, gender and ZWJ variants.
+/// Virtual RC only accepts standard unicode emoji, which is what this set contains
+static EMOJI_GRAPHEMES: Lazy<HashSet<&'static str>> = Lazy::new(|| {
+    emojic::grouped::all_variants()
+        .flatten()
+        .map(|emoji| emoji.grapheme)
+        .collect()
+});
+
+/// Returns the emoji as Virtual RC accepts it, or None if it is not a standard unicode emoji
+pub fn validate(grapheme: &str) -> Option<String> {
+    if EMOJI_GRAPHEMES.contains(grapheme) {
+        return Some(grapheme.into());
+    }
+    // Only add the selector to symbols, so that text such as © is not mistaken for the ©️ emoji
+    let is_symbol = grapheme.chars().next().is_some_and(|c| c >= '\u{2000}');
+    let with_selector = format!("{grapheme}{VARIATION_SELECTOR_16}");
+    match is_symbol && EMOJI_GRAPHEMES.contains(with_selector.as_str()) {
+        true => Some(with_selector),
+        false => None,
+    }
+}
+
+/// Splits a leading unicode emoji from the rest of the input.
+///
+/// The emoji is a whole grapheme cluster so ZWJ sequences (👩‍💻), skin tones (👋🏽) and flags (🇨🇦)
+/// are kept together
+pub fn split_leading(input: &str) -> Option<(String, &str)> {
+    let input = input.trim_start();
+    let grapheme = input.graphemes(true).next()?;
+    let emoji = validate(grapheme)?;
+    Some((emoji, input[grapheme.len()..].trim_start()))
+}
+
+/// Resolves a standard emoji alias such as `red_apple` (without colons)
+pub fn parse_standard_alias(alias: &str) -> Option<String> {
+    emojic::parse_alias(&format!(":{alias}:")).map(|emoji| emoji.grapheme.into())
+}
+
+/// The name of an emoji alias written with colons, e.g. `:sadparrot:` -> `sadparrot`
+pub fn alias_name(emoji: &str) -> Option<&str> {
+    emoji
+        .strip_prefix(':')
+        .and_then(|e| e.strip_suffix(':'))
+        .filter(|name| !name.is_empty())
+}
+
+/// The unicode emoji Virtual RC desks show for Zulip realm custom emoji, set by maintainers
+///
+/// [Custom emoji name] -> [Unicode emoji]
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(transparent)]
+pub struct EmojiFallbacks(pub BTreeMap<String, String>);
+
+impl EmojiFallbacks {
+    pub fn get(&self, name: &str) -> Option<&String> {
+        self.0.get(name)
+    }
+}
+
+/// Zulip emoji aliases and the unicode emojis they map to
+#[derive(Debug, Default)]
+pub struct EmojiTable {
+    /// [Zulip alias] -> [Unicode emoji]
+    unicode: HashMap<String, String>,
+    /// [Unicode emoji] -> [Zulip alias with colons]
+    aliases: HashMap<String, String>,
+}
+
+impl EmojiTable {
+    /// Builds the table from a [`ZulipEmoji`] map. Values are either unicode emojis (🍎) or
+    /// comma separated standard aliases (:red_apple:), the first known alias is used
+    pub fn new(emojis: &ZulipEmoji) -> EmojiTable {
+        let mut table = EmojiTable::default();
+        for (zulip_alias, value) in &emojis.0 {
+            let unicode = value.split(COMMA).find_map(|part| {
+                match emojic::parse_alias(part) {
+                    Some(emoji) => Some(emoji.grapheme.to_string()),
+                    // Not an alias, so this is the unicode emoji itself
+                    None if alias_name(part).is_none() && !part.is_empty() => Some(part.into()),
+                    None => None,
+                }
+            });
+            let Some(unicode) = unicode else {
+                continue;
+            };
+            // Zulip does not render every raw unicode emoji, so replies use the Zulip alias
+            table
+                .aliases
+                .insert(unicode.clone(), format!(":{zulip_alias}:"));
+            table.unicode.insert(zulip_alias.clone(), unicode);
+        }
+        table
+    }
+
+    /// The unicode emoji of a Zulip alias (without colons)
+    pub fn unicode(&self, zulip_alias: &str) -> Option<&String> {
+        self.unicode.get(zulip_alias)
+    }
+
+    /// The Zulip alias (`:crab:`) of a unicode emoji
+    pub fn zulip_alias(&self, unicode: &str) -> Option<&String> {
+        self.aliases.get(unicode)
+    }
+
+    /// Searches Zulip aliases and unicode emoji names (`red apple`) for the query, by substring
+    /// then by edit distance. Returns at most `limit` emojis, best matches first
+    pub fn search(&self, query: &str, limit: usize) -> Vec<EmojiMatch> {
+        let query = alias_name(query.trim())
+            .unwrap_or(query.trim())
+            .to_lowercase()
+            .replace([' ', '-'], "_");
+        if query.is_empty() {
+            return vec![];
+        }
+        let zulip_emojis = self.unicode.iter().map(|(name, unicode)| EmojiMatch {
+            name: name.clone(),
+            unicode: unicode.clone(),
+            alias: Some(format!(":{name}:")),
+        });
+        let unicode_emojis = emojic::grouped::base_emojis().map(|emoji| EmojiMatch {
+            name: emoji.name.to_lowercase().replace([' ', '-'], "_"),
+            unicode: emoji.grapheme.into(),
+            alias: self.zulip_alias(emoji.grapheme).cloned(),
+        });
+        let mut matches: Vec<(usize, EmojiMatch)> = zulip_emojis
+            .chain(unicode_emojis)
+            .filter_map(|candidate| {
+                let score = match_score(&query, &candidate.name)?;
+                Some((score, candidate))
+            })
+            .collect();
+        matches.sort_by(|(a_score, a), (b_score, b)| {
+            (a_score, a.name.len(), &a.name).cmp(&(b_score, b.name.len(), &b.name))
+        });
+        let mut seen = HashSet::new();
+        matches
+            .into_iter()
+            .map(|(_, candidate)| candidate)
+            .filter(|candidate| seen.insert(candidate.unicode.clone()))
+            .take(limit)
+            .collect()
+    }
+}
+
+/// An emoji found by [`EmojiTable::search`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmojiMatch {
+    /// The Zulip alias or unicode name that matched, e.g. `red_apple`
+    pub name: String,
+    pub unicode: String,
+    /// The Zulip alias with colons, None when Zulip has no alias for this emoji
+    pub alias: Option<String>,
+}
+
+/// Lower is a better match. None when the name does not match at all
+fn match_score(query: &str, name: &str) -> Option<usize> {
+    if name == query {
+        return Some(0);
+    }
+    if name.starts_with(query) {
+        return Some(1);
+    }
+    if name.contains(query) {
+        return Some(2);
+    }
+    // Typos are only looked for in longer queries, short ones are too close to everything
+    let max_distance = match query.chars().count() {
+        0..=3 => 0,
+        4..=5 => 1,
+        _ => 2,
+    };
+    let distance = strsim::osa_distance(query, name);
+    match distance <= max_distance {
+        true => Some(3 + distance),
+        false => None,
+    }
+}
+
+/// The emoji table compiled into the binary
+pub fn bundled_table() -> ZulipEmoji {
+    serde_json::from_str(BUNDLED_TABLE).expect("the bundled zulip.json is valid")
+}
+
+/// Loads an emoji table from a file path or an http(s) URL
+pub async fn load_table(client: &HttpsClient, source: &str) -> Result<ZulipEmoji> {
+    let bytes = match source.starts_with("https://") || source.starts_with("http://") {
+        true => {
+            let req = Request::builder()
+                .method(Method::GET)
+                .uri(source)
+                .body(hyper::Body::empty())?;
+            let res = client.request(req).await?;
+            if res.status() != StatusCode::OK {
+                return Err(format!("GET {source} returned HTTP {}", res.status()).into());
+            }
+            hyper::body::to_bytes(res.into_body()).await?.to_vec()
+        }
+        false => tokio::fs::read(source).await?,
+    };
+    let emojis: ZulipEmoji = serde_json::from_slice(&bytes)?;
+    if emojis.0.is_empty() {
+        return Err(format!("The emoji table at {source} is empty").into());
+    }
+    Ok(emojis)
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Tests                                   */
+/* -------------------------------------------------------------------------- */
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use std::collections::{HashMap, HashSet};
+
+    use crate::zulip::ZulipEmoji;
+
+    use super::{alias_name, bundled_table, parse_standard_alias, split_leading, EmojiTable};
+
+    static TABLE: once_cell::sync::Lazy<EmojiTable> =
+        once_cell::sync::Lazy::new(|| EmojiTable::new(&bundled_table()));
+
+    #[test_case("🦀 Rewriting Status Bot" => Some(("🦀".into(), "Rewriting Status Bot".into())) ; "test single codepoint emoji")]
+    #[test_case("👩‍💻 coding" => Some(("👩‍💻".into(), "coding".into())) ; "test zwj sequence")]
+    #[test_case("👋🏽 hello" => Some(("👋🏽".into(), "hello".into())) ; "test skin tone modifier")]
+    #[test_case("🇨🇦 in Canada" => Some(("🇨🇦".into(), "in Canada".into())) ; "test flag")]
+    #[test_case("❤ pairing" => Some(("❤️".into(), "pairing".into())) ; "test missing variation selector")]
+    #[test_case("🦀" => Some(("🦀".into(), "".into())) ; "test emoji only")]
+    #[test_case("🦀🦀 crabs" => Some(("🦀".into(), "🦀 crabs".into())) ; "test only the first emoji")]
+    #[test_case("© 2024" => None ; "test text symbol is not an emoji")]
+    #[test_case("1 on 1" => None ; "test digit is not an emoji")]
+    #[test_case("hello 🦀" => None ; "test emoji must lead")]
+    #[test_case("" => None ; "test empty input")]
+    fn test_split_leading(input: &str) -> Option<(String, String)> {
+        split_leading(input).map(|(emoji, rest)| (emoji, rest.into()))
+    }
+
+    #[test_case("red_apple" => Some("🍎".into()) ; "test standard alias")]
+    #[test_case("crab" => Some("🦀".into()) ; "test standard alias shared with zulip")]
+    #[test_case("sadparrot" => None ; "test custom emoji")]
+    fn test_parse_standard_alias(alias: &str) -> Option<String> {
+        parse_standard_alias(alias)
+    }
+
+    #[test_case(":sadparrot:" => Some("sadparrot") ; "test alias")]
+    #[test_case("::" => None ; "test empty alias")]
+    #[test_case("🦜" => None ; "test unicode emoji")]
+    fn test_alias_name(emoji: &str) -> Option<&str> {
+        alias_name(emoji)
+    }
+
+    #[test_case("apple" => Some("🍎".into()) ; "test standard alias value")]
+    #[test_case("thumbs_up" => Some("👍".into()) ; "test first known alias value")]
+    #[test_case("new_emoji" => Some("🫎".into()) ; "test unicode value")]
+    #[test_case("broken" => None ; "test unknown alias value")]
+    fn test_emoji_table(zulip_alias: &str) -> Option<String> {
+        let emojis = ZulipEmoji(HashMap::from([
+            ("apple".into(), ":red_apple:".into()),
+            ("thumbs_up".into(), ":not_an_alias:,:+1:".into()),
+            ("new_emoji".into(), "🫎".into()),
+            ("broken".into(), ":not_an_alias:".into()),
+        ]));
+        let table = EmojiTable::new(&emojis);
+        if let Some(unicode) = table.unicode(zulip_alias) {
+            assert_eq!(
+                table.zulip_alias(unicode),
+                Some(&format!(":{zulip_alias}:"))
+            );
+        }
+        table.unicode(zulip_alias).cloned()
+    }
+
+    #[test]
+    fn test_bundled_table() {
+        assert_eq!(TABLE.unicode("apple").map(String::as_str), Some("🍎"));
+    }
+
+    #[test_case("crab" => Some(":crab:".into()) ; "test exact alias")]
+    #[test_case(":crab:" => Some(":crab:".into()) ; "test alias with colons")]
+    #[test_case("thermom" => Some(":thermometer:".into()) ; "test prefix")]
+    #[test_case("red apple" => Some(":apple:".into()) ; "test unicode name gives the zulip alias")]
+    #[test_case("octopsu" => Some(":octopus:".into()) ; "test typo")]
+    #[test_case("zzzzzz" => None ; "test no match")]
+    #[test_case("  " => None ; "test empty query")]
+    fn test_search(query: &str) -> Option<String> {
+        TABLE
+            .search(query, 5)
+            .into_iter()
+            .next()
+            .and_then(|found| found.alias)
+    }
+
+    #[test]
+    fn test_search_limit_and_unique_emojis() {
+        let found = TABLE.search("face", 10);
+        assert_eq!(found.len(), 10);
+        let emojis: HashSet<&String> = found.iter().map(|f| &f.unicode).collect();
+        assert_eq!(emojis.len(), 10);
+    }
+}
@@ -0,0 +1,284 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    sync::RwLock,
+};
+
+use serde::{Deserialize, Serialize};
+use time::{format_description::well_known::Iso8601, Duration, OffsetDateTime};
+
+use crate::{bot::Status, consts::*, store::data_path, Result};
+
+/// Which command applied a status
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    Status,
+    Pair,
+    Reuse,
+    /// Set by someone else with `status for`
+    Delegate,
+}
+
+impl Source {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Source::Status => "status",
+            Source::Pair => "pair",
+            Source::Reuse => "reuse",
+            Source::Delegate => "delegate",
+        }
+    }
+}
+
+/// The format of the `export` command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+/// A status which was applied to a desk
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    /// The Zulip user id of the person who applied the status
+    pub zulip_id: u64,
+    pub status: Status,
+    #[serde(with = "time::serde::iso8601")]
+    pub applied_at: OffsetDateTime,
+    pub source: Source,
+}
+
+/// An append-only log of every status applied through Status Bot
+///
+/// Entries are stored one JSON object per line (JSON Lines) so that recording a status only
+/// appends to the file. Entries older than the retention period are dropped when the log is
+/// opened and whenever the oldest entry falls out of the retention period.
+#[derive(Debug)]
+pub struct History {
+    path: PathBuf,
+    /// How long entries are kept
+    retention: Duration,
+    /// Oldest entries first
+    entries: RwLock<Vec<HistoryEntry>>,
+}
+
+impl History {
+    /// Opens the history log in the data directory, keeping `retention_days` of entries
+    pub fn open(retention_days: i64) -> Self {
+        let path = data_path(HISTORY_FILE);
+        let entries = fs::read_to_string(&path)
+            .map(|lines| {
+                lines
+                    .lines()
+                    .filter_map(|line| match serde_json::from_str(line) {
+                        Ok(entry) => Some(entry),
+                        Err(e) => {
+                            error!("history -> open -> skipping unreadable entry. Err = {e}");
+                            None
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let history = Self {
+            path,
+            retention: Duration::days(retention_days),
+            entries: RwLock::new(entries),
+        };
+        if let Err(e) = history.compact(OffsetDateTime::now_utc()) {
+            error!("history -> open -> failed to apply retention. Err = {e}");
+        }
+        history
+    }
+
+    /// Appends an entry to the log
+    ///
+    /// The lock is held while writing, so a concurrent compaction cannot rewrite the file without
+    /// the new line
+    pub fn append(&self, entry: HistoryEntry) -> Result<()> {
+        let now = entry.applied_at;
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+        entries.push(entry);
+        let expired = entries
+            .first()
+            .is_some_and(|oldest| oldest.applied_at < now - self.retention);
+        if expired {
+            self.compact_entries(&mut entries, now)?;
+        }
+        Ok(())
+    }
+
+    /// The most recent entries of a user, most recent first
+    pub fn recent(&self, zulip_id: u64, n: usize) -> Vec<HistoryEntry> {
+        let entries = self.entries.read().unwrap_or_else(|e| e.into_inner());
+        entries
+            .iter()
+            .rev()
+            .filter(|entry| entry.zulip_id == zulip_id)
+            .take(n)
+            .cloned()
+            .collect()
+    }
+
+    /// Every entry of a user, oldest first
+    pub fn for_user(&self, zulip_id: u64) -> Vec<HistoryEntry> {
+        let entries = self.entries.read().unwrap_or_else(|e| e.into_inner());
+        entries
+            .iter()
+            .filter(|entry| entry.zulip_id == zulip_id)
+            .cloned()
+            .collect()
+    }
+
+    /// Drops entries older than the retention period and rewrites the log
+    fn compact(&self, now: OffsetDateTime) -> Result<()> {
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        self.compact_entries(&mut entries, now)
+    }
+
+    /// Compacts with the entries lock already held
+    fn compact_entries(&self, entries: &mut Vec<HistoryEntry>, now: OffsetDateTime) -> Result<()> {
+        let before = entries.len();
+        entries.retain(|entry| entry.applied_at >= now - self.retention);
+        if entries.len() == before {
+            return Ok(());
+        }
+        let mut lines = String::new();
+        for entry in entries.iter() {
+            lines.push_str(&serde_json::to_string(entry)?);
+            lines.push('\n');
+        }
+        let tmp = self.path.with_extension("jsonl.tmp");
+        fs::write(&tmp, lines)?;
+        fs::rename(&tmp, &self.path)?;
+        debug!(
+            "history -> compact -> dropped {} entries",
+            before - entries.len()
+        );
+        Ok(())
+    }
+}
+
+/// Renders history entries for the `export` command. When the export does not fit in a Zulip
+/// message of `max_len` characters, only the most recent entries are exported
+pub fn export(entries: &[HistoryEntry], format: ExportFormat, max_len: usize) -> Result<String> {
+    let render = |entries: &[HistoryEntry]| -> Result<String> {
+        Ok(match format {
+            ExportFormat::Json => {
+                format!("```json\n{}\n```", serde_json::to_string_pretty(entries)?)
+            }
+            ExportFormat::Csv => format!("```csv\n{}```", to_csv(entries)),
+        })
+    };
+    let full = render(entries)?;
+    if full.chars().count() <= max_len {
+        return Ok(full);
+    }
+    // Leaves room for the note, the count of entries is at most as long as the total
+    let note = |kept: usize| {
+        format!(
+            "Only your {kept} most recent of {} statuses fit in a message\n",
+            entries.len()
+        )
+    };
+    let budget = max_len.saturating_sub(note(entries.len()).chars().count());
+    // The export gets shorter as more of the oldest entries are skipped, find the fewest to skip
+    let (mut fits, mut too_long) = (entries.len(), 0);
+    while fits - too_long > 1 {
+        let skip = too_long + (fits - too_long) / 2;
+        match render(&entries[skip..])?.chars().count() <= budget {
+            true => fits = skip,
+            false => too_long = skip,
+        }
+    }
+    let kept = &entries[fits..];
+    Ok(format!("{}{}", note(kept.len()), render(kept)?))
+}
+
+/// Renders history entries as CSV with a header row
+pub fn to_csv(entries: &[HistoryEntry]) -> String {
+    let quote = |field: &str| format!("\"{}\"", field.replace('"', "\"\""));
+    let format_time = |dt: OffsetDateTime| dt.format(&Iso8601::DEFAULT).unwrap_or_default();
+    let mut csv = String::from("applied_at,source,emoji,status,expires_at\n");
+    for entry in entries {
+        let row = [
+            format_time(entry.applied_at),
+            entry.source.as_str().into(),
+            entry.status.emoji.clone().unwrap_or_default(),
+            entry.status.status.clone().unwrap_or_default(),
+            entry.status.expires_at.map(format_time).unwrap_or_default(),
+        ];
+        let row: Vec<String> = row.iter().map(|field| quote(field)).collect();
+        csv.push_str(&row.join(COMMA));
+        csv.push('\n');
+    }
+    csv
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Tests                                   */
+/* -------------------------------------------------------------------------- */
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+    use time::macros::datetime;
+
+    use super::{export, to_csv, ExportFormat, HistoryEntry, Source};
+    use crate::bot::Status;
+
+    #[test_case(None, None => "\"2023-11-29T20:00:00.000000000Z\",\"status\",\"\",\"\",\"\"\n" ; "test empty status")]
+    #[test_case(Some("🦀"), Some("Writing \"Rust\", again") => "\"2023-11-29T20:00:00.000000000Z\",\"status\",\"🦀\",\"Writing \"\"Rust\"\", again\",\"\"\n" ; "test quotes and commas are escaped")]
+    fn test_to_csv(emoji: Option<&str>, status: Option<&str>) -> String {
+        let entry = HistoryEntry {
+            zulip_id: 1,
+            status: Status {
+                emoji: emoji.map(|e| e.into()),
+                status: status.map(|s| s.into()),
+                expires_at: None,
+            },
+            applied_at: datetime!(2023-11-29 20:00:00 UTC),
+            source: Source::Status,
+        };
+        let csv = to_csv(&[entry]);
+        // Skip the header row
+        csv.split_once('\n').unwrap().1.into()
+    }
+
+    fn entries(n: usize) -> Vec<HistoryEntry> {
+        (0..n)
+            .map(|i| HistoryEntry {
+                zulip_id: 1,
+                status: Status {
+                    emoji: Some("🦀".into()),
+                    status: Some(format!("Status number {i}")),
+                    expires_at: None,
+                },
+                applied_at: datetime!(2023-11-29 20:00:00 UTC),
+                source: Source::Status,
+            })
+            .collect()
+    }
+
+    #[test_case(ExportFormat::Json ; "test json")]
+    #[test_case(ExportFormat::Csv ; "test csv")]
+    fn test_export_fits_in_a_message(format: ExportFormat) {
+        let small = export(&entries(2), format, 10_000).unwrap();
+        assert!(small.starts_with("```"));
+
+        let large = export(&entries(1_000), format, 10_000).unwrap();
+        assert!(large.chars().count() <= 10_000);
+        assert!(large.starts_with("Only your "));
+        assert!(large.contains("Status number 999"));
+        assert!(!large.contains("Status number 0\""));
+    }
+}
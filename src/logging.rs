@@ -0,0 +1,412 @@
+use std::{
+    cell::RefCell,
+    collections::hash_map::RandomState,
+    fmt::Display,
+    future::Future,
+    hash::{BuildHasher, Hasher},
+    io::Write,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use env_filter::{self as filter, Filter};
+use hyper::http::request::Builder;
+use log::{Level, Log, Metadata, Record};
+use once_cell::sync::OnceCell;
+use serde_json::{json, Map, Value};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use crate::{
+    consts::*,
+    secret,
+    telemetry::{self, FinishedSpan},
+};
+
+/// Target of the lines logged when a [`Span`] ends, so `RUST_LOG=statusbot=info` includes them
+const SPAN_TARGET: &str = "statusbot::span";
+/// Header sent to Virtual RC and Zulip so their logs can be matched with ours
+const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+static LOGGER: OnceCell<Logger> = OnceCell::new();
+static ID_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+tokio::task_local! {
+    /// The incoming request the current task is handling
+    static CONTEXT: Context;
+}
+
+struct Context {
+    request_id: String,
+    /// Ids of the open spans, the last one is the parent of new spans
+    spans: RefCell<Vec<String>>,
+}
+
+/// How each line is written to stderr
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// One JSON object per line, for fly.io
+    Json,
+    /// Human readable lines, for local development
+    Text,
+}
+
+impl Format {
+    /// LOG_FORMAT, or JSON when running on fly.io
+    fn from_env() -> Format {
+        match std::env::var(LOG_FORMAT).as_deref() {
+            Ok("json") => Format::Json,
+            Ok("text") => Format::Text,
+            _ if std::env::var(FLY_APP_NAME).is_ok() => Format::Json,
+            _ => Format::Text,
+        }
+    }
+}
+
+/// One log line before it is formatted
+#[derive(Debug)]
+struct Line<'a> {
+    level: Level,
+    target: &'a str,
+    message: String,
+    request_id: Option<String>,
+    /// The name and duration of a finished [`Span`]
+    span: Option<(&'a str, Duration)>,
+}
+
+impl Line<'_> {
+    fn format(&self, format: Format, ts: OffsetDateTime) -> String {
+        let ts = ts.format(&Rfc3339).unwrap_or_default();
+        // Secrets can end up in messages through errors or debug output of requests
+        let message = secret::redact(&self.message);
+        match format {
+            Format::Json => {
+                let mut line = Map::new();
+                line.insert("ts".into(), ts.into());
+                line.insert("level".into(), self.level.as_str().into());
+                line.insert("target".into(), self.target.into());
+                line.insert("msg".into(), message.into());
+                if let Some(request_id) = &self.request_id {
+                    line.insert("request_id".into(), request_id.as_str().into());
+                }
+                if let Some((name, duration)) = self.span {
+                    line.insert("span".into(), name.into());
+                    line.insert("duration_ms".into(), json!(duration_ms(duration)));
+                }
+                Value::Object(line).to_string()
+            }
+            Format::Text => {
+                let request_id = self
+                    .request_id
+                    .as_ref()
+                    .map(|id| format!(" [{id}]"))
+                    .unwrap_or_default();
+                format!(
+                    "{ts} {:<5} {}{request_id} > {message}",
+                    self.level, self.target
+                )
+            }
+        }
+    }
+}
+
+fn duration_ms(duration: Duration) -> f64 {
+    (duration.as_secs_f64() * 1000.0 * 1000.0).round() / 1000.0
+}
+
+struct Logger {
+    format: Format,
+    /// The levels set by RUST_LOG, e.g. `info,statusbot::rc=debug,hyper=warn`, with the syntax of
+    /// `env_logger`. Nothing but errors are logged without RUST_LOG
+    filter: Filter,
+}
+
+impl Logger {
+    fn write(&self, line: Line) {
+        let line = line.format(self.format, OffsetDateTime::now_utc());
+        // Logging must never take the bot down
+        let _ = writeln!(std::io::stderr().lock(), "{line}");
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.filter.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        self.write(Line {
+            level: record.level(),
+            target: record.target(),
+            message: record.args().to_string(),
+            request_id: request_id(),
+            span: None,
+        });
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stderr().flush();
+    }
+}
+
+/// Installs the logger. The format is set by LOG_FORMAT (`json` or `text`) and the levels by
+/// RUST_LOG. Calling it again does nothing
+pub fn init() {
+    let logger = LOGGER.get_or_init(|| Logger {
+        format: Format::from_env(),
+        filter: filter(std::env::var(RUST_LOG).ok().as_deref()),
+    });
+    if log::set_logger(logger).is_ok() {
+        log::set_max_level(logger.filter.filter());
+    }
+}
+
+fn filter(spec: Option<&str>) -> Filter {
+    let mut builder = filter::Builder::new();
+    if let Some(spec) = spec {
+        builder.parse(spec);
+    }
+    builder.build()
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                 Request IDs                                */
+/* -------------------------------------------------------------------------- */
+
+/// A new id for an incoming request. It is 128 random bits so it doubles as the OTLP trace id
+pub fn new_request_id() -> String {
+    format!("{:016x}{:016x}", random_u64(), random_u64())
+}
+
+/// Random enough for ids, without pulling in a uuid or rand crate
+fn random_u64() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(ID_COUNTER.fetch_add(1, Ordering::Relaxed));
+    hasher.finish()
+}
+
+/// Runs the future with the request id, which is added to every line it logs and every request it
+/// makes to Virtual RC and Zulip. Spans started by the future belong to the same trace
+pub async fn with_request_id<F: Future>(request_id: String, future: F) -> F::Output {
+    let context = Context {
+        request_id,
+        spans: RefCell::new(Vec::new()),
+    };
+    CONTEXT.scope(context, future).await
+}
+
+/// The id of the request being handled, if any
+pub fn request_id() -> Option<String> {
+    CONTEXT.try_with(|c| c.request_id.clone()).ok()
+}
+
+/// Adds the id of the request being handled to an outgoing request
+pub fn request_id_header(builder: Builder) -> Builder {
+    match request_id() {
+        Some(request_id) => builder.header(REQUEST_ID_HEADER, request_id),
+        None => builder,
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Spans                                   */
+/* -------------------------------------------------------------------------- */
+
+/// Times a piece of work. How long it took is logged at info level when the span is dropped, and
+/// the span is exported when OTLP export is enabled.
+///
+/// A span started while another one of the same request is open is its child. Spans started
+/// outside of a request each start their own trace
+#[must_use = "the span ends as soon as it is dropped"]
+pub struct Span {
+    name: &'static str,
+    id: String,
+    parent_id: Option<String>,
+    trace_id: String,
+    start: Instant,
+    started_at: OffsetDateTime,
+    attributes: Vec<(&'static str, String)>,
+    error: Option<String>,
+}
+
+pub fn span(name: &'static str) -> Span {
+    let id = format!("{:016x}", random_u64());
+    let (trace_id, parent_id) = CONTEXT
+        .try_with(|c| {
+            let mut spans = c.spans.borrow_mut();
+            let parent_id = spans.last().cloned();
+            spans.push(id.clone());
+            (c.request_id.clone(), parent_id)
+        })
+        .unwrap_or_else(|_| (new_request_id(), None));
+    Span {
+        name,
+        id,
+        parent_id,
+        trace_id,
+        start: Instant::now(),
+        started_at: OffsetDateTime::now_utc(),
+        attributes: Vec::new(),
+        error: None,
+    }
+}
+
+impl Span {
+    /// Adds an attribute to the exported span, e.g. the id of a desk
+    pub fn attr(&mut self, key: &'static str, value: impl ToString) {
+        self.attributes.push((key, value.to_string()));
+    }
+
+    /// Marks the span as failed
+    pub fn fail(&mut self, error: impl Display) {
+        self.error = Some(error.to_string());
+    }
+}
+
+impl Drop for Span {
+    fn drop(&mut self) {
+        let _ = CONTEXT.try_with(|c| c.spans.borrow_mut().retain(|id| *id != self.id));
+        let duration = self.start.elapsed();
+        if let Some(logger) = LOGGER.get() {
+            let metadata = Metadata::builder()
+                .level(Level::Info)
+                .target(SPAN_TARGET)
+                .build();
+            if logger.filter.enabled(&metadata) {
+                let failed = self
+                    .error
+                    .as_ref()
+                    .map(|e| format!(", failed: {e}"))
+                    .unwrap_or_default();
+                logger.write(Line {
+                    level: Level::Info,
+                    target: SPAN_TARGET,
+                    message: format!("{} took {}ms{failed}", self.name, duration_ms(duration)),
+                    request_id: request_id(),
+                    span: Some((self.name, duration)),
+                });
+            }
+        }
+        telemetry::record(FinishedSpan {
+            name: self.name,
+            trace_id: std::mem::take(&mut self.trace_id),
+            span_id: std::mem::take(&mut self.id),
+            parent_id: self.parent_id.take(),
+            start: self.started_at,
+            end: self.started_at + duration,
+            attributes: std::mem::take(&mut self.attributes),
+            error: self.error.take(),
+        });
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Tests                                   */
+/* -------------------------------------------------------------------------- */
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use log::{Level, LevelFilter, Metadata};
+    use test_case::test_case;
+    use time::macros::datetime;
+
+    use super::{new_request_id, span, with_request_id, Format, Line};
+    use crate::secret::Secret;
+
+    #[test_case(None, "statusbot::bot" => LevelFilter::Error ; "test errors only by default")]
+    #[test_case(Some("info"), "statusbot::bot" => LevelFilter::Info ; "test default level")]
+    #[test_case(Some("info,statusbot::rc=debug"), "statusbot::rc" => LevelFilter::Debug ; "test target level")]
+    #[test_case(Some("statusbot=warn,statusbot::rc=trace"), "statusbot::rc" => LevelFilter::Trace ; "test most specific target wins")]
+    #[test_case(Some("hyper"), "hyper::client" => LevelFilter::Trace ; "test bare target")]
+    #[test_case(Some("debug,hyper=loud"), "hyper" => LevelFilter::Debug ; "test invalid level is ignored")]
+    fn test_filter(spec: Option<&str>, target: &str) -> LevelFilter {
+        let filter = super::filter(spec);
+        // The most verbose level enabled for the target
+        [
+            Level::Trace,
+            Level::Debug,
+            Level::Info,
+            Level::Warn,
+            Level::Error,
+        ]
+        .into_iter()
+        .find(|level| {
+            let metadata = Metadata::builder().level(*level).target(target).build();
+            filter.enabled(&metadata)
+        })
+        .map_or(LevelFilter::Off, |level| level.to_level_filter())
+    }
+
+    #[test_case(Format::Json, None, None
+        => r#"{"level":"INFO","msg":"message from user = help","target":"statusbot","ts":"2024-01-09T10:30:00Z"}"# ; "test json")]
+    #[test_case(Format::Json, Some("abc123"), Some(("respond", Duration::from_micros(12_345)))
+        => r#"{"duration_ms":12.345,"level":"INFO","msg":"message from user = help","request_id":"abc123","span":"respond","target":"statusbot","ts":"2024-01-09T10:30:00Z"}"# ; "test json with request id and span")]
+    #[test_case(Format::Text, Some("abc123"), None
+        => "2024-01-09T10:30:00Z INFO  statusbot [abc123] > message from user = help" ; "test text")]
+    fn test_format(
+        format: Format,
+        request_id: Option<&str>,
+        span: Option<(&str, Duration)>,
+    ) -> String {
+        Line {
+            level: Level::Info,
+            target: "statusbot",
+            message: "message from user = help".into(),
+            request_id: request_id.map(Into::into),
+            span,
+        }
+        .format(format, datetime!(2024-01-09 10:30 UTC))
+    }
+
+    #[test]
+    fn test_format_redacts_secrets() {
+        let _ = Secret::from("logging-test-secret");
+        let line = Line {
+            level: Level::Error,
+            target: "statusbot::rc",
+            message: "request failed, secret = logging-test-secret".into(),
+            request_id: None,
+            span: None,
+        };
+        assert!(!line
+            .format(Format::Json, datetime!(2024-01-09 10:30 UTC))
+            .contains("logging-test-secret"));
+    }
+
+    #[tokio::test]
+    async fn test_request_id() {
+        assert_eq!(super::request_id(), None);
+        let id = new_request_id();
+        assert_eq!(id.len(), 32);
+        assert_ne!(id, new_request_id());
+        let request_id = with_request_id(id.clone(), async { super::request_id() }).await;
+        assert_eq!(request_id, Some(id));
+    }
+
+    #[tokio::test]
+    async fn test_span_parents() {
+        let id = new_request_id();
+        let (root, child, sibling) = with_request_id(id.clone(), async {
+            let root = span("webhook");
+            let child = span("parse");
+            let child_ids = (child.parent_id.clone(), child.trace_id.clone());
+            drop(child);
+            let sibling = span("rc.get_desks");
+            (root.id.clone(), child_ids, sibling.parent_id.clone())
+        })
+        .await;
+        assert_eq!(child, (Some(root.clone()), id));
+        assert_eq!(sibling, Some(root));
+    }
+
+    #[test]
+    fn test_span_outside_request() {
+        let first = span("cache_desk_owners");
+        let second = span("cache_custom_emojis");
+        assert_eq!(first.parent_id, None);
+        assert_ne!(first.trace_id, second.trace_id);
+    }
+}
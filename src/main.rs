@@ -1,17 +1,34 @@
 // -----------------
 // Crate Modules
 // -----------------
+mod auth;
 mod bot;
+mod cli;
+mod config;
 mod consts;
+mod delegation;
+mod desk_choice;
+mod digest;
+mod emoji;
+mod history;
+mod logging;
+mod pairing;
+mod parser;
 mod rc;
+mod registry;
 mod secret;
+mod stats;
+mod store;
+mod supervisor;
+mod team;
+mod telemetry;
+mod validation;
 mod zulip;
 
 // -----------------
 // External Crates
 // -----------------
 extern crate dotenv;
-extern crate pretty_env_logger;
 #[macro_use]
 extern crate log;
 
@@ -20,16 +37,18 @@ extern crate log;
 // -----------------
 use crate::{
     bot::Bot,
+    cli::{Cli, USAGE},
+    config::Config,
     consts::*,
-    zulip::{OutgoingWebhook, ZulipEmoji},
+    supervisor::Supervisor,
+    zulip::OutgoingWebhook,
 };
 use hyper::{
     service::{make_service_fn, service_fn},
     Body, Client, Method, Request, Response, Server, StatusCode,
 };
 use hyper_tls::HttpsConnector;
-use std::{net::SocketAddr, sync::Arc};
-use tokio::task;
+use std::{net::SocketAddr, sync::Arc, time::Duration};
 
 // -----------------
 // Types
@@ -41,10 +60,15 @@ type HttpsClient = Client<hyper_tls::HttpsConnector<hyper::client::HttpConnector
 /// Match the incoming HTTP request Method and URI and call the corresponding handler
 ///
 /// Each handler should be async (meaning it returns a Future)
-async fn handlers(req: Request<Body>, bot: Arc<Bot>) -> Result<Response<Body>> {
+async fn handlers(
+    req: Request<Body>,
+    bot: Arc<Bot>,
+    supervisor: Arc<Supervisor>,
+) -> Result<Response<Body>> {
     match (req.method(), req.uri().path()) {
         (&Method::POST, STATUS_ENDPOINT) => handle_post_status(req, bot).await,
         (&Method::GET, ROOT) => handle_get_root(req, bot).await,
+        (&Method::GET, READY_ENDPOINT) => handle_get_ready(supervisor).await,
         // Return a basic 404 status code and text body for all other endpoints
         // TODO: Made this a better 404
         _ => Ok(Response::builder()
@@ -61,6 +85,22 @@ async fn handle_get_root(_req: Request<Body>, _bot: Arc<Bot>) -> Result<Response
     Ok(Response::new(Body::from("Hello World!")))
 }
 
+/// Handle an incoming GET request to /ready
+///
+/// Unlike the heartbeat, this fails (HTTP 503) while a background task is restarting or the server
+/// is shutting down, so fly.io stops routing traffic to it
+async fn handle_get_ready(supervisor: Arc<Supervisor>) -> Result<Response<Body>> {
+    let readiness = supervisor.readiness();
+    let status = match readiness.ready {
+        true => StatusCode::OK,
+        false => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    Ok(Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(serde_json::to_string(&readiness)?.into())?)
+}
+
 /// Handle an outgoing webhook (from Zulip to us) when Status Bot is mentioned in a chat.
 ///
 /// Becuase we need to reply to Zulip's API, we will need access to a hyper::Client
@@ -70,6 +110,11 @@ async fn handle_get_root(_req: Request<Body>, _bot: Arc<Bot>) -> Result<Response
 ///
 /// https://recurse.zulipchat.com/api/outgoing-webhooks#outgoing-webhook-format
 async fn handle_post_status(req: Request<Body>, bot: Arc<Bot>) -> Result<Response<Body>> {
+    logging::with_request_id(logging::new_request_id(), post_status(req, bot)).await
+}
+
+async fn post_status(req: Request<Body>, bot: Arc<Bot>) -> Result<Response<Body>> {
+    let _span = logging::span("webhook");
     debug!("incoming req for POST /status");
 
     // --> Receive outgoing webhook from Zulip
@@ -105,7 +150,10 @@ async fn handle_post_status(req: Request<Body>, bot: Arc<Bot>) -> Result<Respons
 /// If RUN_MODE is set to DEVEL, then .env.devel is loaded
 ///
 /// For everything else we default to loading .env.devel
-fn load_env() {
+///
+/// This runs before the logger is installed, since the file may configure it, so failures are
+/// returned for the caller to log
+fn load_env() -> std::result::Result<(), String> {
     let env_file = match std::env::var(RUN_MODE) {
         Ok(val) => match val.as_str() {
             PROD => ENV_PROD,
@@ -117,64 +165,111 @@ fn load_env() {
     // Only load the .env files directly if we are NOT running in FLY
     //
     match std::env::var(FLY_APP_NAME) {
-        Ok(_) => {
-            info!("Inside fly.io, loading variables from environment")
-        }
-        Err(_) => {
-            info!("Not running in PROD on Fly.io");
-            match dotenv::from_filename(env_file) {
-                Ok(_path) => info!("Loaded {env_file} file successfully"),
-                Err(e) => error!("Failed to load {env_file} file with error = {e:?}"),
-            };
-        }
+        // Inside fly.io, the variables are already in the environment
+        Ok(_) => Ok(()),
+        Err(_) => match dotenv::from_filename(env_file) {
+            Ok(_path) => Ok(()),
+            Err(e) => Err(format!("Failed to load {env_file} file with error = {e:?}")),
+        },
     }
 }
 
-fn make_address() -> SocketAddr {
-    let server_domain =
-        std::env::var(SERVER_DOMAIN).expect("The .env file is missing SERVER_DOMAIN");
-    let server_port = std::env::var(SERVER_PORT).expect("The .env file is missing SERVER_PORT");
-    format!("{server_domain}:{server_port}")
-        .parse()
-        .expect("SERVER_DOMAIN:SERVER_PORT is an invalid SocketAddr")
-}
-
 #[tokio::main]
 async fn main() -> Result<()> {
-    pretty_env_logger::init();
-    load_env();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = match Cli::parse(&args) {
+        Ok(Cli::Help) => {
+            println!("{USAGE}");
+            return Ok(());
+        }
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+
+    // LOG_FORMAT and RUST_LOG may come from the .env file
+    let loaded = load_env();
+    logging::init();
+    if let Err(e) = loaded {
+        error!("{e}");
+    }
+
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    };
+    if command == Cli::CheckConfig {
+        println!("The configuration is valid");
+        return Ok(());
+    }
 
-    let address = make_address();
+    let address = config.address;
     let https = HttpsConnector::new();
 
     // Shared State
-    let file = include_str!("zulip.json");
-    let emoji: ZulipEmoji = serde_json::from_str(file).unwrap();
     let client = Client::builder().build::<_, hyper::Body>(https);
-    let bot_singleton = Bot::new(client.clone(), emoji);
+    if let Some(otlp) = config.otlp.clone() {
+        if let Err(e) = telemetry::init(client.clone(), otlp) {
+            error!("Failed to start exporting spans. Err = {e}");
+        }
+    }
+    let bot_singleton = Bot::new(client.clone(), emoji::bundled_table(), config);
     let bot = Arc::new(bot_singleton);
+    if bot.is_dry_run() {
+        warn!("DRY_RUN is set, statuses are only previewed and Virtual RC is left untouched");
+    }
+    // Keep the bundled emoji table when EMOJI_TABLE cannot be loaded
+    if let Err(e) = bot.reload_emojis().await {
+        error!("Failed to load the emoji table. Err = {e}");
+    }
 
-    let bot_for_background_task = bot.clone();
-    let _task_handle = task::spawn(async move {
-        let bot = bot_for_background_task.clone();
-        let mut interval = tokio::time::interval(
-            std::time::Duration::new(DESKS_INTERVAL, 0), /* 5 minutes */
-        );
-
-        loop {
-            interval.tick().await;
-            // go here after 5 minutes
-            let res = bot.cache_desk_owners().await;
-            debug!("cache_desk_owners result = {res:?}");
+    if command != Cli::Serve {
+        match cli::run(command, &bot).await {
+            Ok(output) => println!("{output}"),
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
         }
+        return Ok(());
+    }
+
+    serve(bot, address).await;
+    Ok(())
+}
+
+/// Runs the background tasks and serves the webhook until a shutdown signal. In-flight commands
+/// are drained before the bot is sent home
+async fn serve(bot: Arc<Bot>, address: SocketAddr) {
+    // After a cold start the first command would otherwise race the first refresh of the desks
+    warm_desk_cache(&bot).await;
+    let supervisor = Arc::new(Supervisor::new());
+    let bot_for_refresh = bot.clone();
+    supervisor.spawn("desk_refresh", move || {
+        refresh_desks(bot_for_refresh.clone())
+    });
+    let bot_for_emojis = bot.clone();
+    supervisor.spawn("custom_emojis", move || {
+        refresh_custom_emojis(bot_for_emojis.clone())
     });
+    if let Some(at) = bot.digest_time() {
+        let bot_for_digest = bot.clone();
+        supervisor.spawn("digest", move || post_digests(bot_for_digest.clone(), at));
+    }
 
     // Define HTTP Service
     let bot_for_hyper = bot.clone();
+    let supervisor_for_hyper = supervisor.clone();
     let http_service = make_service_fn(move |_| {
         // Hyper creates a new closure will be created for every incoming connection.
         // Additionally, once a connection is established, there may be multiple HTTP requests.
         let bot = bot_for_hyper.clone();
+        let supervisor = supervisor_for_hyper.clone();
 
         // This is the `Service` that will handle the connection.
         // `service_fn` is a helper to convert a function that
@@ -184,19 +279,135 @@ async fn main() -> Result<()> {
             Ok::<_, GenericError>(service_fn(move |req| {
                 // Handle requests here
                 let bot = bot.clone();
-                handlers(req, bot)
+                let supervisor = supervisor.clone();
+                handlers(req, bot, supervisor)
             }))
         };
         service
     });
 
     info!("Started server on address: {address}");
-    let server = Server::bind(&address).serve(http_service);
+    let (drain, draining) = tokio::sync::oneshot::channel::<()>();
+    let server = Server::bind(&address)
+        .serve(http_service)
+        .with_graceful_shutdown(async {
+            let _ = draining.await;
+        });
+    tokio::pin!(server);
 
-    match server.await {
-        Ok(_) => info!("Server exited successfully"),
-        Err(e) => error!("Server exited with error: {e:?}"),
+    tokio::select! {
+        result = &mut server => match result {
+            Ok(_) => info!("Server exited successfully"),
+            Err(e) => error!("Server exited with error: {e:?}"),
+        },
+        signal = shutdown_signal() => {
+            info!("Received {signal}, draining in-flight commands");
+            supervisor.begin_shutdown();
+            let _ = drain.send(());
+            match tokio::time::timeout(DRAIN_TIMEOUT, &mut server).await {
+                Ok(Ok(_)) => info!("Drained in-flight commands"),
+                Ok(Err(e)) => error!("Server exited with error: {e:?}"),
+                Err(_) => warn!("In-flight commands did not finish within {DRAIN_TIMEOUT:?}"),
+            }
+        }
+    }
+
+    supervisor.shutdown().await;
+    // A command interrupted mid `update_desk` can leave the bot parked beside someone's desk
+    if let Err(e) = bot.send_bot_home().await {
+        error!("Failed to send the bot home on shutdown. Err = {e}");
+    }
+    info!("Shut down");
+}
+
+/// Waits for the first successful refresh of the desks, up to DESKS_WARMUP_TIMEOUT. Serving starts
+/// anyway after the timeout, lookups then refresh the desks on demand
+async fn warm_desk_cache(bot: &Bot) {
+    let warm_up = async {
+        loop {
+            match bot.cache_desk_owners().await {
+                Ok(()) => return,
+                Err(e) => {
+                    warn!("Failed to cache the desks before serving, retrying. Err = {e}");
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+            }
+        }
     };
+    let warm_up = logging::with_request_id(logging::new_request_id(), warm_up);
+    match tokio::time::timeout(DESKS_WARMUP_TIMEOUT, warm_up).await {
+        Ok(()) => info!("Cached the desks"),
+        Err(_) => {
+            warn!("The desks were not cached within {DESKS_WARMUP_TIMEOUT:?}, serving anyway")
+        }
+    }
+}
 
-    Ok(())
+/// Refreshes the desks every DESKS_INTERVAL
+async fn refresh_desks(bot: Arc<Bot>) {
+    // The desks were just cached by `warm_desk_cache`, the first refresh is one interval later
+    let period = Duration::from_secs(DESKS_INTERVAL);
+    let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    loop {
+        interval.tick().await;
+        logging::with_request_id(logging::new_request_id(), async {
+            let res = bot.cache_desk_owners().await;
+            debug!("cache_desk_owners result = {res:?}");
+        })
+        .await;
+    }
+}
+
+/// Refreshes the custom emojis of the Zulip realm every CUSTOM_EMOJIS_INTERVAL
+async fn refresh_custom_emojis(bot: Arc<Bot>) {
+    let mut interval = tokio::time::interval(Duration::from_secs(CUSTOM_EMOJIS_INTERVAL));
+    loop {
+        interval.tick().await;
+        logging::with_request_id(logging::new_request_id(), async {
+            if let Err(e) = bot.cache_custom_emojis().await {
+                warn!("Failed to refresh the custom emojis of Zulip. Err = {e}");
+            }
+        })
+        .await;
+    }
+}
+
+/// Posts the daily digest at the given time of day (UTC)
+async fn post_digests(bot: Arc<Bot>, at: time::Time) {
+    loop {
+        let now = time::OffsetDateTime::now_utc();
+        let wait = digest::next_run(now, at) - now;
+        tokio::time::sleep(wait.unsigned_abs()).await;
+        if let Err(e) = bot.post_digest().await {
+            error!("Failed to post the daily digest. Err = {e}");
+        }
+    }
+}
+
+/// Waits for Ctrl-C or SIGTERM (sent by fly.io before stopping a machine), and names it
+async fn shutdown_signal() -> &'static str {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("Failed to listen for Ctrl-C. Err = {e}");
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                error!("Failed to listen for SIGTERM. Err = {e}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => "Ctrl-C",
+        _ = terminate => "SIGTERM",
+    }
 }
@@ -0,0 +1,139 @@
+use serde::{Deserialize, Serialize};
+use time::{Duration, OffsetDateTime};
+
+/// Words ignored when comparing pairing topics
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "in", "on", "or", "the", "with", "some", "my",
+];
+
+/// A beacon tells others that a Zulip user is available to pair on a topic until it expires
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Beacon {
+    /// The Zulip user id of the person looking to pair
+    pub zulip_id: u64,
+    /// The Zulip full name of the person looking to pair
+    pub name: String,
+    /// The Virtual RC desk of the person looking to pair
+    pub desk_id: usize,
+    /// What they would like to pair on
+    pub topic: String,
+    #[serde(with = "time::serde::iso8601")]
+    pub expires_at: OffsetDateTime,
+}
+
+/// Every beacon that was lit with the `pair` command
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(transparent)]
+pub struct Beacons(pub Vec<Beacon>);
+
+impl Beacons {
+    /// Replaces any previous beacon of the same user and drops beacons which expired
+    pub fn light(&mut self, beacon: Beacon, now: OffsetDateTime) {
+        self.0
+            .retain(|b| b.zulip_id != beacon.zulip_id && b.expires_at > now);
+        self.0.push(beacon);
+    }
+
+    /// Beacons which have not expired yet
+    pub fn active(&self, now: OffsetDateTime) -> Vec<&Beacon> {
+        self.0.iter().filter(|b| b.expires_at > now).collect()
+    }
+
+    /// Active beacons of other users with a topic similar to the given beacon
+    pub fn matches(&self, beacon: &Beacon, now: OffsetDateTime) -> Vec<&Beacon> {
+        self.active(now)
+            .into_iter()
+            .filter(|b| b.zulip_id != beacon.zulip_id && topics_match(&b.topic, &beacon.topic))
+            .collect()
+    }
+}
+
+/// Splits the arguments of `pair` into the topic and an optional trailing duration
+///
+/// `pair rust async 2h` -> ("rust async", 2 hours)
+pub fn parse_pair_args(input: &str) -> (String, Option<Duration>) {
+    match input.trim().rsplit_once(char::is_whitespace) {
+        Some((topic, last)) => match parse_duration(last) {
+            Some(duration) => (topic.trim().into(), Some(duration)),
+            None => (input.trim().into(), None),
+        },
+        None => match parse_duration(input) {
+            Some(duration) => (String::new(), Some(duration)),
+            None => (input.trim().into(), None),
+        },
+    }
+}
+
+/// Parses a short duration such as `45m` or `2h`.
+///
+/// Virtual RC statuses cannot be set more than a day ahead, so longer durations are capped to 24h
+pub fn parse_duration(input: &str) -> Option<Duration> {
+    let input = input.trim();
+    // The amount is capped before building the duration, which panics on overflow
+    let duration = if let Some(minutes) = input.strip_suffix('m') {
+        Duration::minutes(minutes.parse::<i64>().ok()?.clamp(0, 24 * 60))
+    } else if let Some(hours) = input.strip_suffix('h') {
+        Duration::hours(hours.parse::<i64>().ok()?.clamp(0, 24))
+    } else {
+        return None;
+    };
+    match duration.is_positive() {
+        true => Some(duration.min(Duration::DAY)),
+        false => None,
+    }
+}
+
+/// Two topics match when they share a (non stop) word, ignoring case
+pub fn topics_match(a: &str, b: &str) -> bool {
+    let words = |topic: &str| -> Vec<String> {
+        topic
+            .split(|c: char| !c.is_alphanumeric() && c != '+' && c != '#')
+            .map(|word| word.to_lowercase())
+            .filter(|word| !word.is_empty() && !STOP_WORDS.contains(&word.as_str()))
+            .collect()
+    };
+    let b = words(b);
+    words(a).iter().any(|word| b.contains(word))
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Tests                                   */
+/* -------------------------------------------------------------------------- */
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+    use time::Duration;
+
+    use super::{parse_duration, parse_pair_args, topics_match};
+
+    #[test_case("30m" => Some(Duration::minutes(30)) ; "test minutes")]
+    #[test_case("2h" => Some(Duration::hours(2)) ; "test hours")]
+    #[test_case("48h" => Some(Duration::DAY) ; "test capped to a day")]
+    #[test_case("9999999999999999h" => Some(Duration::DAY) ; "test huge hours capped to a day")]
+    #[test_case("9999999999999999m" => Some(Duration::DAY) ; "test huge minutes capped to a day")]
+    #[test_case("-9999999999999999h" => None ; "test huge negative hours")]
+    #[test_case("0h" => None ; "test zero duration")]
+    #[test_case("2d" => None ; "test unknown unit")]
+    #[test_case("h" => None ; "test missing amount")]
+    #[test_case("" => None ; "test empty duration")]
+    #[test_case("🍐" => None ; "test emoji is not a duration")]
+    fn test_parse_duration(input: &str) -> Option<Duration> {
+        parse_duration(input)
+    }
+
+    #[test_case("rust async" => ("rust async".into(), None) ; "test topic only")]
+    #[test_case("rust async 2h" => ("rust async".into(), Some(Duration::hours(2))) ; "test topic and duration")]
+    #[test_case("leetcode" => ("leetcode".into(), None) ; "test single word topic")]
+    #[test_case("90m" => ("".into(), Some(Duration::minutes(90))) ; "test duration only")]
+    fn test_parse_pair_args(input: &str) -> (String, Option<Duration>) {
+        parse_pair_args(input)
+    }
+
+    #[test_case("Rust async", "async python" => true ; "test shared word ignoring case")]
+    #[test_case("C++ and the STL", "c++ templates" => true ; "test symbols in words")]
+    #[test_case("work on the compiler", "on the beach" => false ; "test only stop words shared")]
+    #[test_case("haskell", "ocaml" => false ; "test different topics")]
+    fn test_topics_match(a: &str, b: &str) -> bool {
+        topics_match(a, b)
+    }
+}
@@ -0,0 +1,216 @@
+use std::fmt::Display;
+
+use crate::registry::{self, CommandSpec, COMMANDS};
+
+/// Opening and closing quotes of a quoted argument. Zulip clients may turn " into smart quotes
+const QUOTES: &[(char, char)] = &[('"', '"'), ('“', '”')];
+
+/// Why a message could not be parsed into a command
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// The first word is not a command
+    Unknown {
+        command: String,
+        suggestion: Option<&'static str>,
+    },
+    /// The arguments of a command are missing or invalid
+    Usage {
+        command: &'static str,
+        problem: String,
+    },
+    UnterminatedQuote,
+}
+
+impl ParseError {
+    pub fn usage(command: &'static str, problem: impl Into<String>) -> ParseError {
+        ParseError::Usage {
+            command,
+            problem: problem.into(),
+        }
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::Unknown {
+                command,
+                suggestion: Some(suggestion),
+            } => write!(
+                f,
+                "Unknown command `{command}`, did you mean `{suggestion}`? Send `help` to see every command"
+            ),
+            ParseError::Unknown {
+                command,
+                suggestion: None,
+            } => write!(
+                f,
+                "Unknown command `{command}`. Send `help` to see every command"
+            ),
+            ParseError::Usage { command, problem } => {
+                let usage = registry::usage(command).unwrap_or_else(|| command.to_string());
+                write!(f, "{problem}\nUsage: `{usage}`")
+            }
+            ParseError::UnterminatedQuote => {
+                write!(f, "A quoted argument is missing its closing `\"`")
+            }
+        }
+    }
+}
+
+/// Resolves an alias (`s`, `?`, ...) to the name of its command. Other words are returned as is
+pub fn resolve_alias(word: &str) -> &str {
+    COMMANDS
+        .iter()
+        .find(|spec| spec.aliases.contains(&word))
+        .map_or(word, |spec| spec.name)
+}
+
+/// The closest enabled command name to an unknown command, if any is close enough to be a typo.
+/// Only commands accepted by `suggestable` are suggested
+pub fn suggest(word: &str, suggestable: impl Fn(&CommandSpec) -> bool) -> Option<&'static str> {
+    let len = word.chars().count();
+    COMMANDS
+        .iter()
+        .filter(|spec| spec.enabled && suggestable(spec))
+        .map(|spec| (strsim::osa_distance(word, spec.name), spec.name))
+        .filter(|(distance, _)| *distance <= 2 && *distance < len)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, name)| name)
+}
+
+/// The arguments following a command.
+///
+/// Arguments are separated by whitespace, and can be quoted to include whitespace:
+/// `team_add "rust club" Jacob Young`. Free text arguments (a status, a name) are taken as is with
+/// [`Args::rest`] so quotes inside of them are kept
+#[derive(Debug, Clone)]
+pub struct Args<'a> {
+    remaining: &'a str,
+}
+
+impl<'a> Args<'a> {
+    pub fn new(input: &'a str) -> Self {
+        Self {
+            remaining: input.trim(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.remaining.is_empty()
+    }
+
+    /// The next argument, without its quotes
+    pub fn next(&mut self) -> Result<Option<String>, ParseError> {
+        let mut chars = self.remaining.chars();
+        let Some(first) = chars.next() else {
+            return Ok(None);
+        };
+        if let Some((_, close)) = QUOTES.iter().find(|(open, _)| *open == first) {
+            let quoted = &self.remaining[first.len_utf8()..];
+            let end = quoted.find(*close).ok_or(ParseError::UnterminatedQuote)?;
+            let arg = quoted[..end].to_string();
+            self.remaining = quoted[end + close.len_utf8()..].trim_start();
+            return Ok(Some(arg));
+        }
+        let end = self
+            .remaining
+            .find(char::is_whitespace)
+            .unwrap_or(self.remaining.len());
+        let arg = self.remaining[..end].to_string();
+        self.remaining = self.remaining[end..].trim_start();
+        Ok(Some(arg))
+    }
+
+    /// The next argument if it is the given word, ignoring case
+    pub fn next_if_eq(&mut self, word: &str) -> bool {
+        let mut peek = self.clone();
+        match peek.next() {
+            Ok(Some(next)) if next.eq_ignore_ascii_case(word) => {
+                *self = peek;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Everything left as one argument. Quotes are only removed when they surround all of it
+    pub fn rest(&mut self) -> String {
+        let rest = std::mem::take(&mut self.remaining);
+        for (open, close) in QUOTES {
+            if let Some(inner) = rest
+                .strip_prefix(*open)
+                .and_then(|rest| rest.strip_suffix(*close))
+            {
+                if !inner.contains(*close) {
+                    return inner.trim().into();
+                }
+            }
+        }
+        rest.into()
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Tests                                   */
+/* -------------------------------------------------------------------------- */
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::{resolve_alias, suggest, Args, ParseError};
+
+    #[test_case("" => Ok(vec![]) ; "test empty input")]
+    #[test_case("  team_add\trust  Jacob " => Ok(vec!["team_add".into(), "rust".into(), "Jacob".into()]) ; "test whitespace")]
+    #[test_case(r#"team_add "rust club" "Jacob Young""# => Ok(vec!["team_add".into(), "rust club".into(), "Jacob Young".into()]) ; "test quoted arguments")]
+    #[test_case("team_add “rust club” Jacob" => Ok(vec!["team_add".into(), "rust club".into(), "Jacob".into()]) ; "test smart quotes")]
+    #[test_case(r#"team_add """# => Ok(vec!["team_add".into(), "".into()]) ; "test empty quotes")]
+    #[test_case(r#"team_add "rust club"# => Err(ParseError::UnterminatedQuote) ; "test unterminated quote")]
+    #[test_case("who Jacob's desk" => Ok(vec!["who".into(), "Jacob's".into(), "desk".into()]) ; "test apostrophe is not a quote")]
+    fn test_tokenize(input: &str) -> Result<Vec<String>, ParseError> {
+        let mut args = Args::new(input);
+        let mut tokens = Vec::new();
+        while let Some(token) = args.next()? {
+            tokens.push(token);
+        }
+        Ok(tokens)
+    }
+
+    #[test_case(r#"Writing "Rust" again"# => "Writing \"Rust\" again" ; "test quotes inside text are kept")]
+    #[test_case(r#""Jacob Young""# => "Jacob Young" ; "test surrounding quotes are removed")]
+    #[test_case(r#""a" and "b""# => "\"a\" and \"b\"" ; "test separate quoted words are kept")]
+    #[test_case(r#"I said "hi"# => "I said \"hi" ; "test unterminated quote in text")]
+    fn test_rest(input: &str) -> String {
+        Args::new(input).rest()
+    }
+
+    #[test_case("s" => "status" ; "test status alias")]
+    #[test_case("?" => "help" ; "test help alias")]
+    #[test_case("show" => "show" ; "test command is not an alias")]
+    #[test_case("stauts" => "stauts" ; "test unknown word")]
+    fn test_resolve_alias(word: &str) -> &str {
+        resolve_alias(word)
+    }
+
+    #[test_case("stauts" => Some("status") ; "test transposed letters")]
+    #[test_case("hlep" => Some("help") ; "test help typo")]
+    #[test_case("histroy" => Some("history") ; "test history typo")]
+    #[test_case("set-name" => Some("set_name") ; "test dash instead of underscore")]
+    #[test_case("pizza" => None ; "test unrelated word")]
+    #[test_case("x" => None ; "test single letter")]
+    #[test_case("admn" => None ; "test hidden commands are not suggested")]
+    #[test_case("claer" => None ; "test disabled commands are not suggested")]
+    fn test_suggest(word: &str) -> Option<&'static str> {
+        suggest(word, |spec| spec.name != "admin")
+    }
+
+    #[test_case(ParseError::Unknown { command: "stauts".into(), suggestion: Some("status") }
+        => "Unknown command `stauts`, did you mean `status`? Send `help` to see every command" ; "test unknown with suggestion")]
+    #[test_case(ParseError::usage("reuse", "`{n}` must be a number greater than 0")
+        => "`{n}` must be a number greater than 0\nUsage: `reuse {n}`" ; "test usage")]
+    #[test_case(ParseError::usage("admin move", "`{x}` must be between 0 and 169")
+        => "`{x}` must be between 0 and 169\nUsage: `admin move {x} {y}`" ; "test sub command usage")]
+    fn test_parse_error_display(error: ParseError) -> String {
+        error.to_string()
+    }
+}
@@ -2,11 +2,12 @@ use data_encoding::BASE64URL;
 use hyper::{body::HttpBody, http::request::Builder, Body, Method, Request, Response, StatusCode};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::json;
-use std::env;
 use time::OffsetDateTime;
 use url::Url;
 
-use crate::{bot::Status, consts::*, secret::Secret, HttpsClient, Result};
+use crate::{
+    bot::Status, config::RcConfig, consts::*, logging, secret::Secret, HttpsClient, Result,
+};
 
 #[derive(Debug)]
 /// Recurse Client makes API requets to Virtual RC
@@ -38,22 +39,13 @@ impl RecurseClient {
     /// It uses the given APP_ID and SECRET as HTTP Basic Auth Username:Password
     ///
     /// It controls the bot with the given BOT_ID
-    pub fn new(client: HttpsClient) -> Self {
-        let app_id: Secret = env::var(RC_APP_ID)
-            .expect("The .env file is missing RC_APP_ID")
-            .into();
-        let secret: Secret = env::var(RC_APP_SECRET)
-            .expect("The .env file is missing RC_APP_SECRET")
-            .into();
-        let bot_id = env::var(RC_BOT_ID).expect("The .env file is missing RC_BOT_ID");
-        let site = env::var(RC_SITE).expect("The .env file is missing RC_SITE");
-        let url = Url::parse(&site).expect("The env variable RC_SITE is not a valid URL");
+    pub fn new(client: HttpsClient, config: &RcConfig) -> Self {
         Self {
-            url,
-            bot_id,
+            url: config.site.clone(),
+            bot_id: config.bot_id.clone(),
             client,
-            app_id,
-            secret,
+            app_id: config.app_id.clone(),
+            secret: config.app_secret.clone(),
         }
     }
 
@@ -66,7 +58,7 @@ impl RecurseClient {
             password = self.secret.to_string(),
         );
         let basic = format!("Basic {}", BASE64URL.encode(credentials.as_bytes()));
-        Request::builder()
+        let builder = Request::builder()
             .method(method)
             .uri(self.url.join(endpoint).unwrap().to_string())
             .header(
@@ -74,7 +66,8 @@ impl RecurseClient {
                 AUTHORIZATION,
                 basic,
             )
-            .header("Content-Type", "application/json")
+            .header("Content-Type", "application/json");
+        logging::request_id_header(builder)
     }
 
     /// Given a position in the grid, attempt
@@ -166,6 +159,7 @@ impl RecurseClient {
     ///
     /// Fetch all desks in Virtual RC
     pub async fn get_desks(&self) -> Result<GetDesksResponse> {
+        let _span = logging::span("rc.get_desks");
         let req = self
             .create_request(Method::GET, API_DESKS)
             .body(Body::empty())?;
@@ -192,6 +186,8 @@ impl RecurseClient {
         desk_pos: &Position,
         status: Status,
     ) -> Result<Desk> {
+        let mut span = logging::span("rc.update_desk");
+        span.attr("desk_id", desk_id);
         let endpoint = format!("{}/{}", API_DESKS, desk_id);
         // let status_json = serde_json::to_string(&status)?;
         let desk_json = json!({
@@ -202,11 +198,19 @@ impl RecurseClient {
         let req_update_desk = self
             .create_request(Method::PATCH, &endpoint)
             .body(Body::from(desk_json))?;
-        debug!("Bot -> update_desk -> request = {:#?}", req_update_desk);
+        // The headers are left out, they hold the app credentials
+        debug!(
+            "Bot -> update_desk -> request = {} {}",
+            req_update_desk.method(),
+            req_update_desk.uri()
+        );
         // Before upating a desk, we have to move the StatusBot instance to the correct location
         // next to the desk. So we try all the surrounding positions.
         for pos in self.surrounding_positions(desk_pos) {
-            if let Ok(_) = self
+            let mut move_span = logging::span("rc.move_bot");
+            move_span.attr("x", pos.x);
+            move_span.attr("y", pos.y);
+            let moved = self
                 .update_bot(UpdateBotRequest {
                     name: None,
                     emoji: None,
@@ -215,8 +219,12 @@ impl RecurseClient {
                     direction: None,
                     can_be_mentioned: None,
                 })
-                .await
-            {
+                .await;
+            if let Err(e) = &moved {
+                move_span.fail(e);
+            }
+            drop(move_span);
+            if moved.is_ok() {
                 match self.client.request(req_update_desk).await {
                     Ok(res) => match res.status() {
                         StatusCode::OK => match Self::read_json_body::<Desk>(res).await {
@@ -284,13 +292,18 @@ impl RecurseClient {
     ///
     /// This can upate the bot's properies, location, etc.
     pub async fn update_bot(&self, update_bot: UpdateBotRequest) -> Result<UpdateBotResponse> {
+        let _span = logging::span("rc.update_bot");
         let body = json!({
             "bot": update_bot,
         });
         let req = self
             .create_request(Method::PATCH, &format!("{}/{}", API_BOTS, self.bot_id))
             .body(Body::from(body.to_string()))?;
-        debug!("Bot -> update_bot -> request = {:#?}", req);
+        debug!(
+            "Bot -> update_bot -> request = {} {} {body}",
+            req.method(),
+            req.uri()
+        );
 
         let res = self.client.request(req).await?;
         let result = match res.status() {
@@ -370,7 +383,7 @@ pub enum Direction {
 }
 
 /// The Position of an entity in Virtual RC
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct Position {
     pub x: usize,
     pub y: usize,
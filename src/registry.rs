@@ -0,0 +1,398 @@
+use crate::consts::*;
+
+/// An argument of a command, as shown in help
+#[derive(Debug)]
+pub struct ArgSpec {
+    /// How the argument is written in the usage, e.g. `{emoji}` or `on|off`
+    pub usage: &'static str,
+    pub description: &'static str,
+    pub optional: bool,
+}
+
+/// A command Status Bot understands. `help` and the usage shown in parse errors are generated
+/// from these
+#[derive(Debug)]
+pub struct CommandSpec {
+    pub name: &'static str,
+    /// Other names the command can be typed as
+    pub aliases: &'static [&'static str],
+    pub args: &'static [ArgSpec],
+    pub description: &'static str,
+    pub examples: &'static [&'static str],
+    /// Disabled commands are never shown in help or suggested
+    pub enabled: bool,
+    /// Commands typed after this one, e.g. `status for`
+    pub subcommands: &'static [CommandSpec],
+}
+
+impl CommandSpec {
+    const fn new(name: &'static str, description: &'static str) -> Self {
+        Self {
+            name,
+            aliases: &[],
+            args: &[],
+            description,
+            examples: &[],
+            enabled: true,
+            subcommands: &[],
+        }
+    }
+
+    const fn aliases(mut self, aliases: &'static [&'static str]) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
+    const fn args(mut self, args: &'static [ArgSpec]) -> Self {
+        self.args = args;
+        self
+    }
+
+    const fn examples(mut self, examples: &'static [&'static str]) -> Self {
+        self.examples = examples;
+        self
+    }
+
+    const fn disabled(mut self) -> Self {
+        self.enabled = false;
+        self
+    }
+
+    const fn subcommands(mut self, subcommands: &'static [CommandSpec]) -> Self {
+        self.subcommands = subcommands;
+        self
+    }
+
+    /// How the command is typed, e.g. `status for @{user} {emoji} {text} {expires_at}`
+    pub fn usage(&self, parent: Option<&CommandSpec>) -> String {
+        parent
+            .map(|parent| parent.name)
+            .into_iter()
+            .chain([self.name])
+            .chain(self.args.iter().map(|arg| arg.usage))
+            .collect::<Vec<_>>()
+            .join(SPACE)
+    }
+}
+
+const fn arg(usage: &'static str, description: &'static str) -> ArgSpec {
+    ArgSpec {
+        usage,
+        description,
+        optional: false,
+    }
+}
+
+const fn optional(usage: &'static str, description: &'static str) -> ArgSpec {
+    ArgSpec {
+        usage,
+        description,
+        optional: true,
+    }
+}
+
+const STATUS_ARGS: &[ArgSpec] = &[
+    optional(
+        "{emoji}",
+        "A unicode emoji (🦀) or its alias (:crab:). Custom emojis (:sadparrot:) only work when a maintainer gave them a fallback",
+    ),
+    optional(
+        "{text}",
+        "Status message for others to see, up to 60 characters. Cannot contain `<` or `>` characters, mentions or links",
+    ),
+    optional(
+        "{expires_at}",
+        "The expiration time for the status (default 30m). Set it in the future using Zulip's [<time> selector](https://zulip.com/help/global-times)",
+    ),
+];
+
+/// Every command, in the order they are shown in help
+pub const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new("status", "Set your status")
+        .aliases(&["s"])
+        .args(STATUS_ARGS)
+        .examples(&[
+            "status :crab: Rewriting Status Bot in Rust <time:2025-01-01T10:00:00-04:00>",
+            "s :coffee: Coffee break",
+        ])
+        .subcommands(&[CommandSpec::new(
+            "for",
+            "Set the status of someone who delegated their desk to you. They get a message about it",
+        )
+        .args(&[
+            arg("@{user}", "A mention of the person who delegated their desk"),
+            optional("{emoji}", "A unicode emoji"),
+            optional("{text}", "Status message for others to see"),
+            optional("{expires_at}", "The expiration time for the status"),
+        ])
+        .examples(&["status for @**Jacob Young** :face_with_thermometer: Out sick today"])]),
+    CommandSpec::new(
+        "preview",
+        "Show a status without setting it, also available as `status --preview`",
+    )
+    .args(STATUS_ARGS)
+    .examples(&[
+        "preview :crab: Rewriting Status Bot in Rust <time:2025-01-01T10:00:00-04:00>",
+        "status --preview :coffee: Coffee break",
+    ]),
+    CommandSpec::new("show", "Display your current status"),
+    CommandSpec::new("clear", "Clear your status").disabled(),
+    CommandSpec::new("emoji", "Find the alias of an emoji")
+        .args(&[arg("{query}", "Part of the alias or name of the emoji")])
+        .examples(&["emoji thermometer", "emoji red apple"]),
+    CommandSpec::new("history", "Show your most recent statuses")
+        .args(&[optional("{n}", "How many statuses to show (default 5)")])
+        .examples(&["history", "history 10"]),
+    CommandSpec::new("reuse", "Set one of your recent statuses again")
+        .args(&[arg("{n}", "The number of the status in `history`")])
+        .examples(&["reuse 2"]),
+    CommandSpec::new("export", "Download your status history")
+        .args(&[optional("json|csv", "The format of the download (default json)")])
+        .examples(&["export csv"]),
+    CommandSpec::new(
+        "delegate",
+        "Let someone else set your status with `status for`, e.g. when you are out sick",
+    )
+    .args(&[arg("@{user}", "A mention of the person")])
+    .examples(&["delegate @**Jacob Young**"]),
+    CommandSpec::new("revoke", "Stop someone from setting your status")
+        .args(&[arg("@{user}", "A mention of the person")])
+        .examples(&["revoke @**Jacob Young**"]),
+    CommandSpec::new(
+        "who",
+        "Show the status of everyone whose Virtual RC name contains `{name}`",
+    )
+    .args(&[arg("{name}", "Part of a Virtual RC name")])
+    .examples(&["who Jacob"])
+    .subcommands(&[
+        CommandSpec::new("is at their desk", "Show everyone who currently has a status")
+            .examples(&["who is at their desk"]),
+    ]),
+    CommandSpec::new(
+        "team",
+        "Show the status of everyone in a team, or list every team",
+    )
+    .args(&[optional("{group}", "The name of the team")])
+    .examples(&["team", "team rustaceans"]),
+    CommandSpec::new("team_add", "Add a Virtual RC name to a team")
+        .args(&[
+            arg("{group}", "The name of the team, quoted if it has spaces"),
+            arg("{name}", "A Virtual RC name"),
+        ])
+        .examples(&[
+            "team_add rustaceans Jacob Young",
+            "team_add \"rust club\" Jacob Young",
+        ]),
+    CommandSpec::new("team_remove", "Remove a Virtual RC name from a team")
+        .args(&[
+            arg("{group}", "The name of the team, quoted if it has spaces"),
+            arg("{name}", "A Virtual RC name"),
+        ])
+        .examples(&["team_remove rustaceans Jacob Young"]),
+    CommandSpec::new(
+        "pair",
+        "Set a :pear: status and let others know you want to pair. Status Bot messages you when someone else wants to pair on a similar topic",
+    )
+    .args(&[
+        arg("{topic}", "What you would like to pair on"),
+        optional(
+            "{duration}",
+            "How long you are available, e.g. `45m` or `2h` (default 1h)",
+        ),
+    ])
+    .examples(&["pair rust async 2h"]),
+    CommandSpec::new("pairs", "Show everyone currently looking to pair"),
+    CommandSpec::new(
+        "digest",
+        "Include your status in the daily digest posted to Zulip",
+    )
+    .args(&[arg("on|off", "Whether to include your status")])
+    .examples(&["digest on"]),
+    CommandSpec::new(
+        "set_name",
+        "Tell Status Bot your Virtual RC name when it cannot find your desk",
+    )
+    .args(&[arg("{name}", "Your name in Virtual RC")])
+    .examples(&["set_name Jacob Young"]),
+    CommandSpec::new("clear_name", "Forget the name set with `set_name`"),
+    CommandSpec::new(
+        "desk",
+        "Choose your desk when your Virtual RC name owns several desks",
+    )
+    .args(&[arg("{desk id}", "The id of your desk, as listed by Status Bot")])
+    .examples(&["desk 42"]),
+    CommandSpec::new("feedback", "Send feedback to the Status Bot maintainers")
+        .args(&[arg("{message}", "Your feedback")])
+        .disabled(),
+    CommandSpec::new("help", "Print this message, or explain a command")
+        .aliases(&["?"])
+        .args(&[optional("{command}", "The command to explain")])
+        .examples(&["help", "help status"]),
+    CommandSpec::new("admin", "Commands for Status Bot maintainers").subcommands(&[
+        CommandSpec::new(
+            "lookup",
+            "Explain how a Zulip username is matched to a Virtual RC desk",
+        )
+        .args(&[arg("{zulip name}", "A Zulip full name")])
+        .examples(&["admin lookup Jacob Young (he/him) (F2'23)"]),
+        CommandSpec::new("set_name", "Set the Virtual RC username of a Zulip user")
+            .args(&[
+                arg("{zulip name}", "A Zulip full name, in quotes when it contains spaces"),
+                arg("{rc name}", "A Virtual RC name, in quotes when it contains spaces"),
+            ])
+            .examples(&[r#"admin set_name "Jacob Young (he/him)" "Jake Young""#]),
+        CommandSpec::new(
+            "release_desk",
+            "Forget who chose a desk with `desk`, so that its owner can choose it",
+        )
+        .args(&[arg("{desk id}", "The id of the desk")])
+        .examples(&["admin release_desk 42"]),
+        CommandSpec::new("refresh_desks", "Refresh the cache of Virtual RC desks now"),
+        CommandSpec::new("send_home", "Send Status Bot to its home position"),
+        CommandSpec::new("move", "Move Status Bot to a position in Virtual RC")
+            .args(&[arg("{x}", "The column"), arg("{y}", "The row")])
+            .examples(&["admin move 10 20"]),
+        CommandSpec::new(
+            "stats",
+            "Show what Status Bot has been doing since it started",
+        ),
+        CommandSpec::new(
+            "missing_desk",
+            "Show the help text users get when their desk is not found",
+        ),
+        CommandSpec::new(
+            "reload_emojis",
+            "Load the emoji table again from EMOJI_TABLE, and the custom emojis from Zulip",
+        ),
+        CommandSpec::new(
+            "emoji_fallback",
+            "List the custom emojis of the realm, or set the unicode emoji Virtual RC desks show for one",
+        )
+        .args(&[
+            optional("{custom emoji}", "A custom emoji of the realm"),
+            optional("{emoji}|none", "A unicode emoji or its alias, none removes the fallback"),
+        ])
+        .examples(&[
+            "admin emoji_fallback",
+            "admin emoji_fallback :sadparrot: 🦜",
+            "admin emoji_fallback :sadparrot: none",
+        ]),
+    ]),
+];
+
+/// Finds a command by its name or alias, followed by the name of a sub command.
+/// Returns the parent command too when a sub command is found
+pub fn find(path: &str) -> Option<(Option<&'static CommandSpec>, &'static CommandSpec)> {
+    let path = path.trim().to_lowercase();
+    let (first, rest) = path.split_once(SPACE).unwrap_or((&path, ""));
+    let spec = COMMANDS
+        .iter()
+        .find(|spec| spec.name == first || spec.aliases.contains(&first))?;
+    match rest.trim() {
+        "" => Some((None, spec)),
+        rest => spec
+            .subcommands
+            .iter()
+            .find(|sub| sub.name == rest)
+            .map(|sub| (Some(spec), sub)),
+    }
+}
+
+/// The usage of a command or sub command, e.g. `usage("status for")`
+pub fn usage(path: &str) -> Option<String> {
+    find(path).map(|(parent, spec)| spec.usage(parent))
+}
+
+/// Lists every command which is enabled and visible to the person asking
+pub fn render_help(visible: impl Fn(&CommandSpec) -> bool) -> String {
+    let mut help = String::from(HELP_HEADER);
+    for spec in COMMANDS.iter().filter(|spec| spec.enabled && visible(spec)) {
+        help.push_str(&format!("* `{}` {}", spec.usage(None), spec.description));
+        if let Some(alias) = spec.aliases.first() {
+            help.push_str(&format!(" (or `{alias}`)"));
+        }
+        help.push('\n');
+        for sub in spec.subcommands.iter().filter(|sub| sub.enabled) {
+            help.push_str(&format!(
+                "  * `{}` {}\n",
+                sub.usage(Some(spec)),
+                sub.description
+            ));
+        }
+    }
+    help.push_str(HELP_FOOTER);
+    help
+}
+
+/// Explains a single command: its arguments, sub commands and examples
+pub fn render_command_help(parent: Option<&CommandSpec>, spec: &CommandSpec) -> String {
+    let mut help = format!("**`{}`**: {}\n", spec.usage(parent), spec.description);
+    if !spec.aliases.is_empty() {
+        let aliases: Vec<String> = spec.aliases.iter().map(|a| format!("`{a}`")).collect();
+        help.push_str(&format!("Also typed as {}\n", aliases.join(", ")));
+    }
+    for arg in spec.args {
+        let optional = if arg.optional { " (optional)" } else { "" };
+        help.push_str(&format!(
+            "* `{}`{optional} {}\n",
+            arg.usage, arg.description
+        ));
+    }
+    for sub in spec.subcommands.iter().filter(|sub| sub.enabled) {
+        help.push_str(&format!(
+            "* `{}` {}\n",
+            sub.usage(Some(spec)),
+            sub.description
+        ));
+    }
+    let examples: Vec<&str> = spec
+        .examples
+        .iter()
+        .chain(spec.subcommands.iter().flat_map(|sub| sub.examples))
+        .copied()
+        .collect();
+    if !examples.is_empty() {
+        help.push_str("\n**Examples**:\n");
+        for example in examples {
+            help.push_str(&format!("* `{example}`\n"));
+        }
+    }
+    help
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Tests                                   */
+/* -------------------------------------------------------------------------- */
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::{find, render_help, usage};
+
+    #[test_case("status" => Some("status {emoji} {text} {expires_at}".into()) ; "test command usage")]
+    #[test_case("s" => Some("status {emoji} {text} {expires_at}".into()) ; "test alias usage")]
+    #[test_case("status for" => Some("status for @{user} {emoji} {text} {expires_at}".into()) ; "test sub command usage")]
+    #[test_case("admin move" => Some("admin move {x} {y}".into()) ; "test admin sub command usage")]
+    #[test_case("who is at their desk" => Some("who is at their desk".into()) ; "test sub command with spaces")]
+    #[test_case("status to" => None ; "test unknown sub command")]
+    #[test_case("stauts" => None ; "test unknown command")]
+    fn test_usage(path: &str) -> Option<String> {
+        usage(path)
+    }
+
+    #[test_case("HELP" => Some("help") ; "test find ignores case")]
+    #[test_case("?" => Some("help") ; "test find alias")]
+    #[test_case("clear" => Some("clear") ; "test find disabled command")]
+    fn test_find(path: &str) -> Option<&'static str> {
+        find(path).map(|(_, spec)| spec.name)
+    }
+
+    #[test_case("`show`" => true ; "test enabled commands are shown")]
+    #[test_case("`clear`" => false ; "test disabled commands are hidden")]
+    #[test_case("`feedback" => false ; "test disabled commands with arguments are hidden")]
+    #[test_case("`admin" => false ; "test commands which are not visible are hidden")]
+    #[test_case("`status for @{user}" => true ; "test sub commands are shown")]
+    fn test_render_help(command: &str) -> bool {
+        render_help(|spec| spec.name != "admin").contains(command)
+    }
+}
@@ -1,13 +1,52 @@
-use std::fmt::{Debug, Display};
+use std::{
+    collections::BTreeSet,
+    fmt::{Debug, Display},
+    sync::RwLock,
+};
 
+use serde::Deserialize;
+
+/// Replaces secrets in logs
+const REDACTED: &str = "[REDACTED]";
+/// Shorter values would redact ordinary words
+const MIN_REDACTED_LEN: usize = 6;
+
+/// Every secret created with [`Secret::new`] or `into()`, so they can be redacted from logs
+static SECRETS: RwLock<BTreeSet<String>> = RwLock::new(BTreeSet::new());
+
+/// A value that is never logged. Secrets created with [`Secret::new`] or `into()` are also redacted
+/// from any log message they end up in
+///
+/// Deserialized secrets (e.g. the token of an incoming webhook) are not registered for redaction,
+/// since anyone can send them
+#[derive(Deserialize, Clone)]
+#[serde(transparent)]
 pub struct Secret(pub String);
 
 impl Secret {
     pub fn new(val: String) -> Self {
+        if val.len() >= MIN_REDACTED_LEN {
+            if let Ok(mut secrets) = SECRETS.write() {
+                secrets.insert(val.clone());
+            }
+        }
         Self(val)
     }
 }
 
+/// Replaces every registered secret in the text
+pub fn redact(text: &str) -> String {
+    let Ok(secrets) = SECRETS.read() else {
+        return text.into();
+    };
+    secrets
+        .iter()
+        .filter(|secret| text.contains(secret.as_str()))
+        .fold(text.to_string(), |text, secret| {
+            text.replace(secret.as_str(), REDACTED)
+        })
+}
+
 impl Display for Secret {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.0)
@@ -19,6 +58,12 @@ impl Debug for Secret {
     }
 }
 
+impl PartialEq for Secret {
+    fn eq(&self, other: &Secret) -> bool {
+        self.0 == other.0
+    }
+}
+
 impl PartialEq<String> for Secret {
     fn eq(&self, other: &String) -> bool {
         self.0 == *other
@@ -33,12 +78,39 @@ impl PartialEq<Secret> for String {
 
 impl From<String> for Secret {
     fn from(value: String) -> Self {
-        Self(value)
+        Self::new(value)
     }
 }
 
 impl From<&str> for Secret {
     fn from(value: &str) -> Self {
-        Self(value.into())
+        Self::new(value.into())
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Tests                                   */
+/* -------------------------------------------------------------------------- */
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::{redact, Secret};
+
+    #[test_case("token=hunter2hunter2" => "token=[REDACTED]" ; "test registered secret")]
+    #[test_case("hunter2hunter2 and hunter2hunter2" => "[REDACTED] and [REDACTED]" ; "test every occurence")]
+    #[test_case("abc is short" => "abc is short" ; "test short values are not redacted")]
+    #[test_case("webhook-token-value" => "webhook-token-value" ; "test deserialized secrets are not registered")]
+    fn test_redact(text: &str) -> String {
+        let _ = Secret::from("hunter2hunter2");
+        let _ = Secret::from("abc");
+        let _: Secret = serde_json::from_str("\"webhook-token-value\"").unwrap();
+        redact(text)
+    }
+
+    #[test]
+    fn test_debug_hides_secret() {
+        let secret = Secret::from("hunter2hunter2");
+        assert_eq!(format!("{secret:?}"), "SECRET_CANNOT_BE_LOGGED");
     }
 }
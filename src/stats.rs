@@ -0,0 +1,79 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        RwLock,
+    },
+    time::Instant,
+};
+
+use time::OffsetDateTime;
+
+/// Counters describing what Status Bot has been doing since it started
+#[derive(Debug)]
+pub struct Stats {
+    started_at: Instant,
+    /// Number of commands run
+    commands: AtomicU64,
+    /// Number of commands which returned an error
+    errors: AtomicU64,
+    /// Number of statuses successfully applied to desks
+    statuses: AtomicU64,
+    /// When the desk cache was last refreshed successfully
+    last_desk_refresh: RwLock<Option<OffsetDateTime>>,
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Self {
+            started_at: Instant::now(),
+            commands: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            statuses: AtomicU64::new(0),
+            last_desk_refresh: RwLock::new(None),
+        }
+    }
+
+    pub fn record_command(&self, is_err: bool) {
+        self.commands.fetch_add(1, Ordering::Relaxed);
+        if is_err {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn record_status(&self) {
+        self.statuses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_desk_refresh(&self, at: OffsetDateTime) {
+        if let Ok(mut last) = self.last_desk_refresh.write() {
+            *last = Some(at);
+        }
+    }
+
+    pub fn last_desk_refresh(&self) -> Option<OffsetDateTime> {
+        self.last_desk_refresh.read().ok().and_then(|last| *last)
+    }
+
+    /// Seconds since Status Bot started
+    pub fn uptime(&self) -> u64 {
+        self.started_at.elapsed().as_secs()
+    }
+
+    pub fn commands(&self) -> u64 {
+        self.commands.load(Ordering::Relaxed)
+    }
+
+    pub fn errors(&self) -> u64 {
+        self.errors.load(Ordering::Relaxed)
+    }
+
+    pub fn statuses(&self) -> u64 {
+        self.statuses.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
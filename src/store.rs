@@ -0,0 +1,138 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+use once_cell::sync::OnceCell;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{consts::*, Result};
+
+/// The data directory (DATA_DIR), set once from the configuration
+static DATA_DIR_PATH: OnceCell<PathBuf> = OnceCell::new();
+
+/// Sets the data directory. Only the first call has an effect, stores must not move once opened
+pub fn set_data_dir(data_dir: PathBuf) {
+    let _ = DATA_DIR_PATH.set(data_dir);
+}
+
+/// The path of a file inside the data directory (DATA_DIR)
+pub fn data_path(file_name: &str) -> PathBuf {
+    DATA_DIR_PATH
+        .get()
+        .map_or_else(|| Path::new(DEFAULT_DATA_DIR), PathBuf::as_path)
+        .join(file_name)
+}
+
+/// A value persisted as a JSON file inside the data directory (DATA_DIR)
+///
+/// The value is loaded once when the store is opened and kept in memory. Every call to
+/// [`Store::update`] writes the whole value back to disk, so stores are meant for small amounts of
+/// bot managed state (groups, opt-ins, ...)
+#[derive(Debug)]
+pub struct Store<T> {
+    /// Location of the JSON file backing this store
+    path: PathBuf,
+    value: RwLock<T>,
+}
+
+impl<T> Store<T>
+where
+    T: Serialize + DeserializeOwned + Default,
+{
+    /// Opens the store saved as `file_name` inside the data directory.
+    ///
+    /// A missing or unreadable file results in an empty (default) store
+    pub fn open(file_name: &str) -> Self {
+        Self::open_path(data_path(file_name))
+    }
+
+    fn open_path(path: PathBuf) -> Self {
+        let value = match fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                error!("store -> open -> failed to parse {path:?}, starting empty. Err = {e}");
+                T::default()
+            }),
+            Err(e) => {
+                debug!("store -> open -> no data at {path:?}, starting empty. Err = {e}");
+                T::default()
+            }
+        };
+        Self {
+            path,
+            value: RwLock::new(value),
+        }
+    }
+
+    /// Reads from the stored value
+    pub fn read<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        let value = self.value.read().unwrap_or_else(|e| e.into_inner());
+        f(&value)
+    }
+
+    /// Modifies the stored value and writes it to disk
+    pub fn update<R>(&self, f: impl FnOnce(&mut T) -> R) -> Result<R> {
+        let mut value = self.value.write().unwrap_or_else(|e| e.into_inner());
+        let result = f(&mut value);
+        self.save(&value)?;
+        Ok(result)
+    }
+
+    /// Writes to a temporary file first and renames it, so a crash never leaves a half written
+    /// JSON file behind
+    fn save(&self, value: &T) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(value)?)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Tests                                   */
+/* -------------------------------------------------------------------------- */
+#[cfg(test)]
+mod tests {
+    use std::{collections::BTreeMap, fs, path::PathBuf};
+
+    use super::Store;
+
+    /// A path in a directory of its own, so tests do not share files
+    fn temp_path(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("statusbot-{test}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir.join("store.json")
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let path = temp_path("store-round-trip");
+        let store: Store<BTreeMap<String, u64>> = Store::open_path(path.clone());
+        store.update(|map| map.insert("desks".into(), 42)).unwrap();
+
+        let reopened: Store<BTreeMap<String, u64>> = Store::open_path(path.clone());
+        assert_eq!(reopened.read(|map| map.get("desks").copied()), Some(42));
+        assert!(!path.with_extension("json.tmp").exists());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn test_missing_file_is_empty() {
+        let store: Store<BTreeMap<String, u64>> = Store::open_path(temp_path("store-missing-file"));
+        assert!(store.read(|map| map.is_empty()));
+    }
+
+    #[test]
+    fn test_unreadable_file_is_empty() {
+        let path = temp_path("store-unreadable-file");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "not json").unwrap();
+        let store: Store<BTreeMap<String, u64>> = Store::open_path(path.clone());
+        assert!(store.read(|map| map.is_empty()));
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,285 @@
+//! Runs the background tasks of Status Bot (refreshing desks, posting the digest), restarts them
+//! when they fail, and stops them on shutdown. Their state is reported by the readiness endpoint
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    time::Duration,
+};
+
+use serde::Serialize;
+use tokio::{sync::watch, task::JoinHandle};
+
+/// Wait before the first restart, doubled for each failure in a row
+const RESTART_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(60);
+
+/// The state of a supervised task
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum TaskState {
+    Running,
+    /// The task panicked or exited, it is started again after a backoff
+    Restarting {
+        failures: u32,
+        error: String,
+    },
+    /// Stopped for shutdown
+    Stopped,
+}
+
+/// What the readiness endpoint reports
+#[derive(Serialize, Debug)]
+pub struct Readiness {
+    pub ready: bool,
+    pub shutting_down: bool,
+    pub tasks: BTreeMap<&'static str, TaskState>,
+}
+
+pub struct Supervisor {
+    tasks: Arc<RwLock<BTreeMap<&'static str, TaskState>>>,
+    handles: RwLock<Vec<JoinHandle<()>>>,
+    shutting_down: AtomicBool,
+    /// Tells every supervised task to stop
+    shutdown: watch::Sender<bool>,
+    backoff: Duration,
+}
+
+impl Supervisor {
+    pub fn new() -> Supervisor {
+        Supervisor::with_backoff(RESTART_BACKOFF)
+    }
+
+    pub fn with_backoff(backoff: Duration) -> Supervisor {
+        Supervisor {
+            tasks: Arc::new(RwLock::new(BTreeMap::new())),
+            handles: RwLock::new(Vec::new()),
+            shutting_down: AtomicBool::new(false),
+            shutdown: watch::channel(false).0,
+            backoff,
+        }
+    }
+
+    /// Runs the task created by `make` until shutdown. Supervised tasks are meant to run forever,
+    /// so the task is created again whenever it panics or exits
+    pub fn spawn<F, Fut>(&self, name: &'static str, make: F)
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let tasks = self.tasks.clone();
+        let mut shutdown = self.shutdown.subscribe();
+        let backoff = self.backoff;
+        let set_state = move |state: TaskState| {
+            if let Ok(mut tasks) = tasks.write() {
+                tasks.insert(name, state);
+            }
+        };
+        set_state(TaskState::Running);
+        let handle = tokio::spawn(async move {
+            let mut failures = 0;
+            loop {
+                let started = tokio::time::Instant::now();
+                let mut task = tokio::spawn(make());
+                let error = tokio::select! {
+                    result = &mut task => match result {
+                        Ok(()) => "the task exited".to_string(),
+                        Err(e) => e.to_string(),
+                    },
+                    _ = stopped(&mut shutdown) => {
+                        task.abort();
+                        let _ = task.await;
+                        set_state(TaskState::Stopped);
+                        return;
+                    }
+                };
+                // A task which ran longer than its last backoff had recovered
+                if failures > 0 && started.elapsed() > restart_backoff(backoff, failures) {
+                    failures = 0;
+                }
+                failures += 1;
+                error!(
+                    "Task {name} failed {failures} time(s) in a row, restarting it. Err = {error}"
+                );
+                set_state(TaskState::Restarting { failures, error });
+                let wait = restart_backoff(backoff, failures);
+                tokio::select! {
+                    _ = tokio::time::sleep(wait) => set_state(TaskState::Running),
+                    _ = stopped(&mut shutdown) => {
+                        set_state(TaskState::Stopped);
+                        return;
+                    }
+                }
+            }
+        });
+        if let Ok(mut handles) = self.handles.write() {
+            handles.push(handle);
+        }
+    }
+
+    /// Reports not ready from now on, so no new traffic is sent while draining
+    pub fn begin_shutdown(&self) {
+        self.shutting_down.store(true, Ordering::SeqCst);
+    }
+
+    /// Stops every supervised task and waits for them
+    pub async fn shutdown(&self) {
+        self.begin_shutdown();
+        let _ = self.shutdown.send(true);
+        let handles = match self.handles.write() {
+            Ok(mut handles) => std::mem::take(&mut *handles),
+            Err(_) => Vec::new(),
+        };
+        for handle in handles {
+            let _ = handle.await;
+        }
+    }
+
+    /// Ready while not shutting down and every task is running
+    pub fn readiness(&self) -> Readiness {
+        let shutting_down = self.shutting_down.load(Ordering::SeqCst);
+        let tasks = self.tasks.read().map(|t| t.clone()).unwrap_or_default();
+        Readiness {
+            ready: !shutting_down && tasks.values().all(|t| *t == TaskState::Running),
+            shutting_down,
+            tasks,
+        }
+    }
+}
+
+/// How long to wait before restarting a task which failed `failures` times in a row
+fn restart_backoff(backoff: Duration, failures: u32) -> Duration {
+    backoff
+        .saturating_mul(2u32.saturating_pow(failures.saturating_sub(1)))
+        .min(MAX_RESTART_BACKOFF)
+}
+
+/// Waits for the shutdown signal
+async fn stopped(shutdown: &mut watch::Receiver<bool>) {
+    // The borrowed value must not be held across awaits of the supervising task
+    let _ = shutdown.wait_for(|stop| *stop).await;
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Tests                                   */
+/* -------------------------------------------------------------------------- */
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicU32, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use super::{Supervisor, TaskState};
+
+    async fn wait_until(mut condition: impl FnMut() -> bool) {
+        for _ in 0..200 {
+            if condition() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("condition was never met");
+    }
+
+    #[tokio::test]
+    async fn test_restarts_after_panic() {
+        let supervisor = Supervisor::with_backoff(Duration::from_millis(50));
+        let runs = Arc::new(AtomicU32::new(0));
+        let task_runs = runs.clone();
+        supervisor.spawn("refresh", move || {
+            let runs = task_runs.clone();
+            async move {
+                if runs.fetch_add(1, Ordering::SeqCst) == 0 {
+                    panic!("refresh failed");
+                }
+                std::future::pending::<()>().await
+            }
+        });
+
+        wait_until(|| !supervisor.readiness().ready).await;
+        let readiness = supervisor.readiness();
+        assert!(matches!(
+            readiness.tasks["refresh"],
+            TaskState::Restarting { failures: 1, .. }
+        ));
+
+        wait_until(|| runs.load(Ordering::SeqCst) == 2).await;
+        assert!(supervisor.readiness().ready);
+    }
+
+    #[tokio::test]
+    async fn test_failures_reset_after_recovery() {
+        let supervisor = Supervisor::with_backoff(Duration::from_millis(20));
+        let runs = Arc::new(AtomicU32::new(0));
+        let task_runs = runs.clone();
+        supervisor.spawn("refresh", move || {
+            let runs = task_runs.clone();
+            async move {
+                match runs.fetch_add(1, Ordering::SeqCst) {
+                    0 => panic!("refresh failed right away"),
+                    // Runs well past the backoff before failing again
+                    1 => {
+                        tokio::time::sleep(Duration::from_millis(200)).await;
+                        panic!("refresh failed later");
+                    }
+                    _ => std::future::pending::<()>().await,
+                }
+            }
+        });
+
+        wait_until(|| runs.load(Ordering::SeqCst) == 2).await;
+        wait_until(|| !supervisor.readiness().ready).await;
+        assert!(matches!(
+            supervisor.readiness().tasks["refresh"],
+            TaskState::Restarting { failures: 1, .. }
+        ));
+    }
+
+    #[test]
+    fn test_restart_backoff() {
+        let backoff = Duration::from_secs(1);
+        assert_eq!(super::restart_backoff(backoff, 1), backoff);
+        assert_eq!(super::restart_backoff(backoff, 3), Duration::from_secs(4));
+        assert_eq!(
+            super::restart_backoff(backoff, 40),
+            super::MAX_RESTART_BACKOFF
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_stops_tasks() {
+        let supervisor = Supervisor::new();
+        supervisor.spawn("digest", std::future::pending);
+        assert!(supervisor.readiness().ready);
+
+        supervisor.begin_shutdown();
+        let readiness = supervisor.readiness();
+        assert!(!readiness.ready);
+        assert!(readiness.shutting_down);
+
+        supervisor.shutdown().await;
+        assert_eq!(supervisor.readiness().tasks["digest"], TaskState::Stopped);
+    }
+
+    #[tokio::test]
+    async fn test_readiness_json() {
+        let supervisor = Supervisor::new();
+        supervisor.spawn("refresh", std::future::pending);
+        let json = serde_json::to_value(supervisor.readiness()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "ready": true,
+                "shutting_down": false,
+                "tasks": { "refresh": { "state": "running" } },
+            })
+        );
+    }
+}
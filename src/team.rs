@@ -0,0 +1,109 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+/// Teams of Virtual RC usernames managed through Status Bot
+///
+/// [Team Name] -> [Virtual RC Usernames]
+#[derive(Serialize, Deserialize, Debug, Default)]
+#[serde(transparent)]
+pub struct Groups(pub BTreeMap<String, BTreeSet<String>>);
+
+impl Groups {
+    /// The members of a group, None if the group does not exist
+    pub fn members(&self, group: &str) -> Option<Vec<String>> {
+        self.0
+            .get(group)
+            .map(|members| members.iter().cloned().collect())
+    }
+
+    /// Adds a member to a group, creating the group if it does not exist yet
+    pub fn add(&mut self, group: &str, rc_username: String) {
+        self.0.entry(group.into()).or_default().insert(rc_username);
+    }
+
+    /// Removes a member from a group. Empty groups are removed entirely.
+    ///
+    /// Returns whether the member was part of the group
+    pub fn remove(&mut self, group: &str, rc_username: &str) -> bool {
+        let Some(members) = self.0.get_mut(group) else {
+            return false;
+        };
+        let before = members.len();
+        members.retain(|member| !member.eq_ignore_ascii_case(rc_username));
+        let removed = members.len() != before;
+        if members.is_empty() {
+            self.0.remove(group);
+        }
+        removed
+    }
+}
+
+/// Renders a Zulip markdown table
+///
+/// Pipes inside of cells are escaped so user provided text cannot break the table
+pub fn render_table<const N: usize>(header: &[&str; N], rows: &[[String; N]]) -> String {
+    let escape = |cell: &str| cell.replace('|', "\\|");
+    let mut table = vec![
+        format!("| {} |", header.join(" | ")),
+        format!("|{}", " --- |".repeat(N)),
+    ];
+    for row in rows {
+        let cells: Vec<String> = row.iter().map(|cell| escape(cell)).collect();
+        table.push(format!("| {} |", cells.join(" | ")));
+    }
+    table.join("\n")
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Tests                                   */
+/* -------------------------------------------------------------------------- */
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::{render_table, Groups};
+
+    fn groups() -> Groups {
+        let mut groups = Groups::default();
+        groups.add("rustaceans", "Jacob Young".into());
+        groups.add("rustaceans", "Jane Doe".into());
+        groups.add("rustaceans", "Jacob Young".into());
+        groups
+    }
+
+    #[test_case("rustaceans" => Some(vec!["Jacob Young".into(), "Jane Doe".into()]) ; "test members are sorted and unique")]
+    #[test_case("gophers" => None ; "test unknown group")]
+    fn test_members(group: &str) -> Option<Vec<String>> {
+        groups().members(group)
+    }
+
+    #[test_case("rustaceans", "jacob young" => (true, Some(1)) ; "test remove ignoring case")]
+    #[test_case("rustaceans", "John Doe" => (false, Some(2)) ; "test remove someone else")]
+    #[test_case("gophers", "Jacob Young" => (false, Some(2)) ; "test remove from unknown group")]
+    fn test_remove(group: &str, rc_username: &str) -> (bool, Option<usize>) {
+        let mut groups = groups();
+        let removed = groups.remove(group, rc_username);
+        (removed, groups.members("rustaceans").map(|m| m.len()))
+    }
+
+    #[test]
+    fn test_remove_last_member_removes_group() {
+        let mut groups = Groups::default();
+        groups.add("gophers", "Jane Doe".into());
+        assert!(groups.remove("gophers", "Jane Doe"));
+        assert!(groups.0.is_empty());
+    }
+
+    #[test]
+    fn test_render_table() {
+        let rows = [
+            ["Jacob Young".into(), "🦀".into()],
+            ["Jane | Doe".into(), "".into()],
+        ];
+        assert_eq!(
+            render_table(&["Name", "Emoji"], &rows),
+            "| Name | Emoji |\n| --- | --- |\n| Jacob Young | 🦀 |\n| Jane \\| Doe |  |"
+        );
+    }
+}
@@ -0,0 +1,345 @@
+//! Exports the spans of [`crate::logging`] to an OpenTelemetry collector over OTLP/HTTP with
+//! JSON encoding: https://opentelemetry.io/docs/specs/otlp/#otlphttp
+//!
+//! Export is enabled by setting OTEL_EXPORTER_OTLP_ENDPOINT, e.g. `http://localhost:4318`
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use hyper::{header::CONTENT_TYPE, Body, Method, Request};
+use once_cell::sync::OnceCell;
+use serde_json::{json, Value};
+use time::OffsetDateTime;
+use tokio::{
+    sync::mpsc::{self, Receiver, Sender},
+    task::JoinHandle,
+};
+
+use crate::{consts::*, secret::Secret, HttpsClient, Result};
+
+/// Path of the traces endpoint, appended to OTEL_EXPORTER_OTLP_ENDPOINT
+const TRACES_PATH: &str = "v1/traces";
+const DEFAULT_SERVICE_NAME: &str = "statusbot";
+/// Spans of one request end close together, waiting a little sends them in one batch
+const EXPORT_DELAY: Duration = Duration::from_secs(2);
+const MAX_BATCH: usize = 512;
+/// Spans waiting for export. More are dropped, so a slow collector cannot grow memory without limit
+const MAX_QUEUED_SPANS: usize = 4 * MAX_BATCH;
+/// How long the collector may take to accept a batch
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+/// https://opentelemetry.io/docs/specs/otel/trace/api/#set-status
+const STATUS_ERROR: u8 = 2;
+const SPAN_KIND_INTERNAL: u8 = 1;
+
+static SPANS: OnceCell<Sender<FinishedSpan>> = OnceCell::new();
+/// Spans dropped because the queue was full, since the last export
+static DROPPED: AtomicU64 = AtomicU64::new(0);
+
+/// A span that ended, see [`crate::logging::Span`]
+#[derive(Debug, Clone)]
+pub struct FinishedSpan {
+    pub name: &'static str,
+    /// 32 hex characters, the request id
+    pub trace_id: String,
+    /// 16 hex characters
+    pub span_id: String,
+    pub parent_id: Option<String>,
+    pub start: OffsetDateTime,
+    pub end: OffsetDateTime,
+    pub attributes: Vec<(&'static str, String)>,
+    pub error: Option<String>,
+}
+
+/// Queues the span for export. Does nothing when export is disabled, and drops the span when the
+/// queue is full
+pub fn record(span: FinishedSpan) {
+    if let Some(spans) = SPANS.get() {
+        if spans.try_send(span).is_err() {
+            DROPPED.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Starts exporting spans to the collector
+pub fn init(client: HttpsClient, config: OtlpConfig) -> Result<JoinHandle<()>> {
+    let exporter = Exporter { client, config };
+    let (sender, receiver) = mpsc::channel(MAX_QUEUED_SPANS);
+    if SPANS.set(sender).is_err() {
+        return Err("OTLP export was already started".into());
+    }
+    info!("Exporting spans to {}", exporter.config.url);
+    Ok(tokio::spawn(exporter.run(receiver)))
+}
+
+/// Where spans are exported to
+#[derive(Debug, Clone)]
+pub struct OtlpConfig {
+    /// The traces endpoint of the collector
+    url: String,
+    service_name: String,
+    /// Extra headers, usually the API key of a hosted collector
+    headers: Vec<(String, Secret)>,
+}
+
+impl OtlpConfig {
+    /// `headers` are `key=value` pairs separated by commas, like OTEL_EXPORTER_OTLP_HEADERS
+    pub fn new(
+        endpoint: &str,
+        service_name: Option<String>,
+        headers: &str,
+    ) -> std::result::Result<OtlpConfig, String> {
+        // Without the trailing slash, joining would replace the last segment of the path
+        let endpoint = format!("{}/", endpoint.trim_end_matches('/'));
+        let url = url::Url::parse(&endpoint)
+            .and_then(|url| url.join(TRACES_PATH))
+            .map_err(|e| {
+                format!("{OTEL_EXPORTER_OTLP_ENDPOINT} must be a URL, got `{endpoint}`: {e}")
+            })?;
+        let headers = headers
+            .split(',')
+            .filter(|header| !header.trim().is_empty())
+            .map(|header| match header.split_once('=') {
+                Some((key, value)) => Ok((key.trim().to_string(), value.trim().into())),
+                None => Err(format!(
+                    "{OTEL_EXPORTER_OTLP_HEADERS} must be key=value pairs, got `{header}`"
+                )),
+            })
+            .collect::<std::result::Result<_, _>>()?;
+        Ok(OtlpConfig {
+            url: url.to_string(),
+            service_name: service_name.unwrap_or_else(|| DEFAULT_SERVICE_NAME.into()),
+            headers,
+        })
+    }
+}
+
+/// Sends batches of spans to an OTLP collector
+pub struct Exporter {
+    client: HttpsClient,
+    config: OtlpConfig,
+}
+
+impl Exporter {
+    /// Exports spans until every sender is dropped
+    async fn run(self, mut spans: Receiver<FinishedSpan>) {
+        while let Some(span) = spans.recv().await {
+            tokio::time::sleep(EXPORT_DELAY).await;
+            let mut batch = vec![span];
+            while batch.len() < MAX_BATCH {
+                match spans.try_recv() {
+                    Ok(span) => batch.push(span),
+                    Err(_) => break,
+                }
+            }
+            if let Err(e) = self.export(&batch).await {
+                warn!("Failed to export {} spans. Err = {e}", batch.len());
+            }
+            let dropped = DROPPED.swap(0, Ordering::Relaxed);
+            if dropped > 0 {
+                warn!("Dropped {dropped} spans, the export queue was full");
+            }
+        }
+    }
+
+    /// POST /v1/traces
+    pub async fn export(&self, spans: &[FinishedSpan]) -> Result<()> {
+        let mut req = Request::builder()
+            .method(Method::POST)
+            .uri(&self.config.url)
+            .header(CONTENT_TYPE, "application/json");
+        for (key, value) in &self.config.headers {
+            req = req.header(key.as_str(), value.to_string());
+        }
+        let req = req.body(Body::from(self.body(spans).to_string()))?;
+        let res = tokio::time::timeout(EXPORT_TIMEOUT, self.client.request(req))
+            .await
+            .map_err(|_| {
+                format!("The OTLP collector did not answer within {EXPORT_TIMEOUT:?}")
+            })??;
+        match res.status() {
+            status if status.is_success() => Ok(()),
+            status => Err(format!("The OTLP collector returned HTTP {status}").into()),
+        }
+    }
+
+    /// An `ExportTraceServiceRequest`
+    fn body(&self, spans: &[FinishedSpan]) -> Value {
+        json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [attribute("service.name", &self.config.service_name)],
+                },
+                "scopeSpans": [{
+                    "scope": { "name": DEFAULT_SERVICE_NAME },
+                    "spans": spans.iter().map(span_json).collect::<Vec<_>>(),
+                }],
+            }],
+        })
+    }
+}
+
+fn span_json(span: &FinishedSpan) -> Value {
+    let mut json = json!({
+        "traceId": span.trace_id,
+        "spanId": span.span_id,
+        "name": span.name,
+        "kind": SPAN_KIND_INTERNAL,
+        // 64 bit integers are strings in the JSON encoding of protobuf
+        "startTimeUnixNano": span.start.unix_timestamp_nanos().to_string(),
+        "endTimeUnixNano": span.end.unix_timestamp_nanos().to_string(),
+        "attributes": span
+            .attributes
+            .iter()
+            .map(|(key, value)| attribute(key, value))
+            .collect::<Vec<_>>(),
+    });
+    if let Some(parent_id) = &span.parent_id {
+        json["parentSpanId"] = parent_id.as_str().into();
+    }
+    if let Some(error) = &span.error {
+        json["status"] = json!({ "code": STATUS_ERROR, "message": error });
+    }
+    json
+}
+
+fn attribute(key: &str, value: &str) -> Value {
+    json!({ "key": key, "value": { "stringValue": value } })
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Tests                                   */
+/* -------------------------------------------------------------------------- */
+#[cfg(test)]
+mod tests {
+    use std::{convert::Infallible, net::SocketAddr};
+
+    use hyper::{
+        service::{make_service_fn, service_fn},
+        Body, Client, Request, Response, Server, StatusCode,
+    };
+    use hyper_tls::HttpsConnector;
+    use serde_json::{json, Value};
+    use test_case::test_case;
+    use time::macros::datetime;
+    use tokio::sync::mpsc::{self, UnboundedReceiver};
+
+    use super::{Exporter, FinishedSpan, OtlpConfig};
+
+    /// Stands in for an OpenTelemetry collector, every request is sent to the receiver
+    fn collector(status: StatusCode) -> (SocketAddr, UnboundedReceiver<(Option<String>, Value)>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let service = make_service_fn(move |_| {
+            let sender = sender.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req: Request<Body>| {
+                    let sender = sender.clone();
+                    async move {
+                        let api_key = req
+                            .headers()
+                            .get("x-api-key")
+                            .and_then(|v| v.to_str().ok())
+                            .map(Into::into);
+                        assert_eq!(req.uri().path(), "/v1/traces");
+                        let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+                        sender
+                            .send((api_key, serde_json::from_slice(&body).unwrap()))
+                            .unwrap();
+                        Ok::<_, Infallible>(
+                            Response::builder()
+                                .status(status)
+                                .body(Body::empty())
+                                .unwrap(),
+                        )
+                    }
+                }))
+            }
+        });
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(service);
+        let address = server.local_addr();
+        tokio::spawn(server);
+        (address, receiver)
+    }
+
+    fn exporter(address: SocketAddr) -> Exporter {
+        let client = Client::builder().build::<_, Body>(HttpsConnector::new());
+        let config = OtlpConfig::new(
+            &format!("http://{address}"),
+            Some("statusbot-test".into()),
+            "x-api-key=collector-key",
+        )
+        .unwrap();
+        Exporter { client, config }
+    }
+
+    fn spans() -> Vec<FinishedSpan> {
+        let webhook = FinishedSpan {
+            name: "webhook",
+            trace_id: "0123456789abcdef0123456789abcdef".into(),
+            span_id: "00000000000000aa".into(),
+            parent_id: None,
+            start: datetime!(2024-01-09 10:30 UTC),
+            end: datetime!(2024-01-09 10:30:02 UTC),
+            attributes: Vec::new(),
+            error: None,
+        };
+        let move_bot = FinishedSpan {
+            name: "rc.move_bot",
+            span_id: "00000000000000bb".into(),
+            parent_id: Some("00000000000000aa".into()),
+            attributes: vec![("x", "10".into()), ("y", "11".into())],
+            error: Some("Must not be in a block".into()),
+            ..webhook.clone()
+        };
+        vec![webhook, move_bot]
+    }
+
+    #[tokio::test]
+    async fn test_export() {
+        let (address, mut requests) = collector(StatusCode::OK);
+        exporter(address).export(&spans()).await.unwrap();
+
+        let (api_key, body) = requests.recv().await.unwrap();
+        assert_eq!(api_key.as_deref(), Some("collector-key"));
+        let resource = &body["resourceSpans"][0];
+        assert_eq!(
+            resource["resource"]["attributes"][0],
+            json!({ "key": "service.name", "value": { "stringValue": "statusbot-test" } })
+        );
+        let exported = &resource["scopeSpans"][0]["spans"];
+        assert_eq!(
+            exported[0],
+            json!({
+                "traceId": "0123456789abcdef0123456789abcdef",
+                "spanId": "00000000000000aa",
+                "name": "webhook",
+                "kind": 1,
+                "startTimeUnixNano": "1704796200000000000",
+                "endTimeUnixNano": "1704796202000000000",
+                "attributes": [],
+            })
+        );
+        assert_eq!(exported[1]["parentSpanId"], "00000000000000aa");
+        assert_eq!(
+            exported[1]["attributes"][1],
+            json!({ "key": "y", "value": { "stringValue": "11" } })
+        );
+        assert_eq!(
+            exported[1]["status"],
+            json!({ "code": 2, "message": "Must not be in a block" })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_collector_error() {
+        let (address, _requests) = collector(StatusCode::SERVICE_UNAVAILABLE);
+        let result = exporter(address).export(&spans()).await;
+        assert!(result.is_err());
+    }
+
+    #[test_case("http://localhost:4318" => "http://localhost:4318/v1/traces" ; "test base url")]
+    #[test_case("https://otel.example.com/otlp/" => "https://otel.example.com/otlp/v1/traces" ; "test path is kept")]
+    fn test_traces_url(endpoint: &str) -> String {
+        OtlpConfig::new(endpoint, None, "").unwrap().url
+    }
+}
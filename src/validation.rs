@@ -0,0 +1,180 @@
+use std::fmt::Display;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{bot::Status, emoji};
+
+/// Zulip caps the text of a user status at 60 characters
+const ZULIP_STATUS_MAX_CHARS: usize = 60;
+/// The longest status text a Virtual RC desk accepts
+const RC_STATUS_MAX_CHARS: usize = 255;
+
+/// Mentions notify people when the status is shown in Zulip: users (@**Jacob Young**), silent
+/// mentions (@_**Jacob Young**), user groups (@*faculty*) and wildcards (@all)
+static RE_MENTION: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)@_?\*|@(all|everyone|stream|channel|topic)\b").unwrap());
+/// Markdown links (`[text](url)`, `![image](url)`) and code blocks can disguise or break a message
+static RE_MARKDOWN: Lazy<Regex> = Lazy::new(|| Regex::new(r"\]\(|```|~~~").unwrap());
+
+/// Where a status is sent or shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// Replies, `who` and the daily digest
+    Zulip,
+    /// The desk itself
+    VirtualRc,
+}
+
+impl Target {
+    fn max_status_chars(&self) -> usize {
+        match self {
+            Target::Zulip => ZULIP_STATUS_MAX_CHARS,
+            Target::VirtualRc => RC_STATUS_MAX_CHARS,
+        }
+    }
+}
+
+impl Display for Target {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Target::Zulip => write!(f, "Zulip"),
+            Target::VirtualRc => write!(f, "Virtual RC"),
+        }
+    }
+}
+
+/// Why a status cannot be sent to a target
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    TooLong { length: usize, max: usize },
+    ControlCharacter(char),
+    Mention,
+    Markdown,
+    InvalidEmoji(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationError {
+    pub target: Target,
+    pub problem: Problem,
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let target = self.target;
+        match &self.problem {
+            Problem::TooLong { length, max } => write!(
+                f,
+                "{target}: the text is {length} characters long, the limit is {max}"
+            ),
+            Problem::ControlCharacter(c) => write!(
+                f,
+                "{target}: the text contains the invisible character U+{:04X}",
+                *c as u32
+            ),
+            Problem::Mention => write!(f, "{target}: the text cannot mention people"),
+            Problem::Markdown => {
+                write!(f, "{target}: the text cannot contain links or code blocks")
+            }
+            Problem::InvalidEmoji(emoji) => write!(f, "{target}: `{emoji}` is not an emoji"),
+        }
+    }
+}
+
+/// Checks a status against the limits of the target. Every problem found is returned
+pub fn validate(status: &Status, target: Target) -> Vec<ValidationError> {
+    let mut problems = Vec::new();
+    if let Some(text) = &status.status {
+        let length = text.chars().count();
+        let max = target.max_status_chars();
+        if length > max {
+            problems.push(Problem::TooLong { length, max });
+        }
+        if let Some(c) = text.chars().find(|c| is_control(*c)) {
+            problems.push(Problem::ControlCharacter(c));
+        }
+        // Virtual RC shows the text as is, only Zulip renders markdown and notifies mentions
+        if target == Target::Zulip && RE_MENTION.is_match(text) {
+            problems.push(Problem::Mention);
+        }
+        if target == Target::Zulip && RE_MARKDOWN.is_match(text) {
+            problems.push(Problem::Markdown);
+        }
+    }
+    if let Some(e) = &status.emoji {
+        let is_valid = match target {
+            // Zulip also shows custom emojis (:sadparrot:)
+            Target::Zulip => is_emoji(e) || emoji::alias_name(e).is_some(),
+            Target::VirtualRc => is_emoji(e),
+        };
+        if !is_valid {
+            problems.push(Problem::InvalidEmoji(e.clone()));
+        }
+    }
+    problems
+        .into_iter()
+        .map(|problem| ValidationError { target, problem })
+        .collect()
+}
+
+/// Control characters, and the bidirectional overrides that can make text read backwards
+fn is_control(c: char) -> bool {
+    c.is_control() || matches!(c, '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}')
+}
+
+/// A single emoji. Emojis newer than emojic are accepted when they are one grapheme of symbols
+fn is_emoji(e: &str) -> bool {
+    e.graphemes(true).count() == 1
+        && (emoji::validate(e).is_some() || !e.chars().any(|c| c.is_ascii() || c.is_alphanumeric()))
+}
+
+/* -------------------------------------------------------------------------- */
+/*                                    Tests                                   */
+/* -------------------------------------------------------------------------- */
+#[cfg(test)]
+mod tests {
+    use test_case::test_case;
+
+    use super::{validate, Problem, Target};
+    use crate::bot::Status;
+
+    fn status(emoji: Option<&str>, text: &str) -> Status {
+        Status {
+            emoji: emoji.map(Into::into),
+            status: Some(text.into()).filter(|t: &String| !t.is_empty()),
+            expires_at: None,
+        }
+    }
+
+    #[test_case(Some("🦀"), "Rewriting Status Bot", Target::Zulip => Vec::<Problem>::new() ; "test valid status")]
+    #[test_case(Some("👩‍💻"), "", Target::VirtualRc => Vec::<Problem>::new() ; "test zwj emoji only")]
+    #[test_case(None, &"a".repeat(61), Target::Zulip => vec![Problem::TooLong { length: 61, max: 60 }] ; "test too long for zulip")]
+    #[test_case(None, &"a".repeat(61), Target::VirtualRc => Vec::<Problem>::new() ; "test long text fits virtual rc")]
+    #[test_case(None, "über café ☕ naïve", Target::Zulip => Vec::<Problem>::new() ; "test length counts characters not bytes")]
+    #[test_case(None, "hello\u{0007}", Target::VirtualRc => vec![Problem::ControlCharacter('\u{0007}')] ; "test control character")]
+    #[test_case(None, "hello \u{202E}olleh", Target::Zulip => vec![Problem::ControlCharacter('\u{202E}')] ; "test bidi override")]
+    #[test_case(None, "pairing with @**Jacob Young**", Target::Zulip => vec![Problem::Mention] ; "test user mention")]
+    #[test_case(None, "pairing with @_**Jacob Young|123**", Target::Zulip => vec![Problem::Mention] ; "test silent mention")]
+    #[test_case(None, "ask @*faculty*", Target::Zulip => vec![Problem::Mention] ; "test group mention")]
+    #[test_case(None, "hello @everyone", Target::Zulip => vec![Problem::Mention] ; "test wildcard mention")]
+    #[test_case(None, "email me at jacob@allthings.dev", Target::Zulip => Vec::<Problem>::new() ; "test email is not a mention")]
+    #[test_case(None, "pairing with @**Jacob Young**", Target::VirtualRc => Vec::<Problem>::new() ; "test mentions are plain text in virtual rc")]
+    #[test_case(None, "[free pizza](https://example.com)", Target::Zulip => vec![Problem::Markdown] ; "test link")]
+    #[test_case(None, "```rust", Target::Zulip => vec![Problem::Markdown] ; "test code block")]
+    #[test_case(None, "writing `cargo` docs", Target::Zulip => Vec::<Problem>::new() ; "test inline code")]
+    #[test_case(Some(":sadparrot:"), "", Target::Zulip => Vec::<Problem>::new() ; "test custom emoji in zulip")]
+    #[test_case(Some(":sadparrot:"), "", Target::VirtualRc => vec![Problem::InvalidEmoji(":sadparrot:".into())] ; "test custom emoji in virtual rc")]
+    #[test_case(Some("ab"), "", Target::Zulip => vec![Problem::InvalidEmoji("ab".into())] ; "test text is not an emoji")]
+    #[test_case(Some("🦀🦀"), "", Target::VirtualRc => vec![Problem::InvalidEmoji("🦀🦀".into())] ; "test two emojis")]
+    fn test_validate(emoji: Option<&str>, text: &str, target: Target) -> Vec<Problem> {
+        validate(&status(emoji, text), target)
+            .into_iter()
+            .map(|e| {
+                assert_eq!(e.target, target);
+                e.problem
+            })
+            .collect()
+    }
+}
@@ -33,26 +33,161 @@
 
 use std::collections::HashMap;
 
-use crate::{secret::Secret, HttpsClient};
-use serde::Deserialize;
+use crate::{config::ZulipConfig, consts::*, logging, secret::Secret, HttpsClient, Result};
+use data_encoding::BASE64;
+use hyper::{http::request::Builder, Body, Method, Request, StatusCode};
+use serde::{de::IgnoredAny, Deserialize};
 use time::OffsetDateTime;
-use url::Url;
+use url::{form_urlencoded, Url};
 
 const BASE_URL: &str = "zulipchat.com";
 
+#[derive(Debug)]
+/// Zulip Client makes API requests to the Zulip instance on behalf of Status Bot
 pub struct ZulipClient {
     /// The base url of the Zulip instance
     ///
     /// url = <subdomain>.zulipchat.com
     pub url: Url,
     client: HttpsClient,
+    /// The Bot's email used as a username for Zulip API requests
+    /// E.g. status1-bot@zulipchat.com
     bot_email: Secret,
+    /// The Zulip Bot's api key
     api_key: Secret,
 }
 
 impl ZulipClient {
-    pub fn new() -> Self {
-        todo!()
+    /// Constructs a new ZulipClient instance configured to connect to <subdomain>.zulipchat.com
+    ///
+    /// It uses the bot's email and API key as HTTP Basic Auth Username:Password
+    pub fn new(client: HttpsClient, config: &ZulipConfig) -> Self {
+        Self {
+            url: config.site.clone(),
+            client,
+            bot_email: config.bot_email.clone(),
+            api_key: config.api_key.clone(),
+        }
+    }
+
+    /// Constructs an http::request::Builder with method, uri, and HTTP Basic Auth.
+    /// The caller must provide .body() and thus consume the RequestBuilder
+    fn create_request(&self, method: Method, endpoint: &str) -> Builder {
+        let credentials = format!("{}:{}", self.bot_email, self.api_key);
+        let basic = format!("Basic {}", BASE64.encode(credentials.as_bytes()));
+        let builder = Request::builder()
+            .method(method)
+            .uri(self.url.join(endpoint).unwrap().to_string())
+            .header(AUTHORIZATION, basic)
+            .header("Content-Type", "application/x-www-form-urlencoded");
+        logging::request_id_header(builder)
+    }
+
+    /* -------------------------------------------------------------------------- */
+    /*                                   API CALLS                                */
+    /* -------------------------------------------------------------------------- */
+
+    /// POST /api/v1/messages
+    ///
+    /// Sends a direct message to the given Zulip user ids
+    pub async fn send_direct_message(&self, user_ids: &[u64], content: &str) -> Result<u64> {
+        let to = serde_json::to_string(user_ids)?;
+        let body = form_urlencoded::Serializer::new(String::new())
+            .append_pair("type", "direct")
+            .append_pair("to", &to)
+            .append_pair("content", content)
+            .finish();
+        self.send_message(body).await
+    }
+
+    /// POST /api/v1/messages
+    ///
+    /// Sends a message to the given stream and topic
+    pub async fn send_stream_message(
+        &self,
+        stream: &str,
+        topic: &str,
+        content: &str,
+    ) -> Result<u64> {
+        let body = form_urlencoded::Serializer::new(String::new())
+            .append_pair("type", "stream")
+            .append_pair("to", stream)
+            .append_pair("topic", topic)
+            .append_pair("content", content)
+            .finish();
+        self.send_message(body).await
+    }
+
+    /// GET /api/v1/realm/emoji
+    ///
+    /// Fetches the custom emoji uploaded to the Zulip realm, leaving out deactivated ones
+    pub async fn get_realm_emoji(&self) -> Result<Vec<RealmEmoji>> {
+        let _span = logging::span("zulip.get_realm_emoji");
+        let req = self
+            .create_request(Method::GET, API_REALM_EMOJI)
+            .body(Body::empty())?;
+        let res = self.client.request(req).await?;
+        let status = res.status();
+        let bytes = hyper::body::to_bytes(res.into_body()).await?;
+        let response: RealmEmojiResponse = serde_json::from_slice(&bytes)?;
+        match (status, response.result.as_str()) {
+            (StatusCode::OK, "success") => Ok(response
+                .emoji
+                .into_values()
+                .filter(|emoji| !emoji.deactivated)
+                .collect()),
+            _ => Err(format!(
+                "The Zulip API failed to list the realm emoji: HTTP {status} {}",
+                response.msg
+            )
+            .into()),
+        }
+    }
+
+    /// GET /api/v1/users
+    ///
+    /// Lists the active users of the realm
+    pub async fn get_users(&self) -> Result<Vec<Member>> {
+        let _span = logging::span("zulip.get_users");
+        let req = self
+            .create_request(Method::GET, API_USERS)
+            .body(Body::empty())?;
+        let res = self.client.request(req).await?;
+        let status = res.status();
+        let bytes = hyper::body::to_bytes(res.into_body()).await?;
+        let response: UsersResponse = serde_json::from_slice(&bytes)?;
+        match (status, response.result.as_str()) {
+            (StatusCode::OK, "success") => Ok(response
+                .members
+                .into_iter()
+                .filter(|member| member.is_active)
+                .collect()),
+            _ => Err(format!(
+                "The Zulip API failed to list the users: HTTP {status} {}",
+                response.msg
+            )
+            .into()),
+        }
+    }
+
+    /// Sends the form encoded message body and returns the id of the new message
+    async fn send_message(&self, body: String) -> Result<u64> {
+        let _span = logging::span("zulip.send_message");
+        let req = self
+            .create_request(Method::POST, API_MESSAGES)
+            .body(Body::from(body))?;
+        let res = self.client.request(req).await?;
+        let status = res.status();
+        let bytes = hyper::body::to_bytes(res.into_body()).await?;
+        let response: ZulipResponse = serde_json::from_slice(&bytes)?;
+        match (status, response.result.as_str(), response.id) {
+            (StatusCode::OK, "success", Some(id)) => Ok(id),
+            _ => Err(format!(
+                "The Zulip API failed to send the message: HTTP {status} {}",
+                response.msg
+            )
+            .into()),
+        }
     }
 }
 
@@ -80,7 +215,7 @@ pub enum ContentType {
     Markdown,
 }
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum MessageType {
     Stream,
@@ -91,19 +226,11 @@ pub enum MessageType {
 #[serde(rename_all = "snake_case")]
 #[serde(untagged)]
 pub enum DisplayRecipient {
-    Recipients(Vec<User>),
+    /// The users of a direct message. Replies only go to the sender, so they are not read
+    Recipients(Vec<IgnoredAny>),
     Stream(String),
 }
 
-#[derive(Deserialize, Debug)]
-#[serde(rename_all = "snake_case")]
-pub struct User {
-    email: String,
-    full_name: String,
-    id: u64,
-    is_mirror_dummy: bool,
-}
-
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "snake_case")]
 pub struct Message {
@@ -159,6 +286,17 @@ pub struct Message {
     pub rendered_content: String,
 }
 
+impl Message {
+    /// The name of the stream this message was sent to.
+    /// Returns None for direct messages
+    pub fn stream_name(&self) -> Option<&str> {
+        match (&self.r#type, &self.display_recipient) {
+            (MessageType::Stream, DisplayRecipient::Stream(name)) => Some(name),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(rename_all = "snake_case")]
 pub struct OutgoingWebhook {
@@ -174,12 +312,44 @@ pub struct OutgoingWebhook {
     /// A string of alphanumeric characters that can be used to authenticate the webhook request
     /// (each bot user uses a fixed token). You can get the token used by a given outgoing webhook
     /// bot in the zuliprc file downloaded when creating the bot.
-    pub token: String,
+    pub token: Secret,
     /// A dictionary containing details on the message that triggered the outgoing webhook, in the
     /// format used by GET /messages.
     pub message: Message,
 }
 
+/// The common envelope of every Zulip REST API response
+///
+/// https://zulip.com/api/rest-error-handling
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub struct ZulipResponse {
+    /// Either "success" or "error"
+    pub result: String,
+    /// An error message, empty on success
+    pub msg: String,
+    /// The id of the newly created message, only present for POST /messages
+    pub id: Option<u64>,
+}
+
+/// Response of GET /api/v1/realm/emoji
+#[derive(Deserialize, Debug)]
+struct RealmEmojiResponse {
+    result: String,
+    msg: String,
+    /// [Emoji id] -> [RealmEmoji]
+    #[serde(default)]
+    emoji: HashMap<String, RealmEmoji>,
+}
+
+/// A custom emoji uploaded to the Zulip realm, e.g. :sadparrot:
+#[derive(Deserialize, Debug, Clone)]
+pub struct RealmEmoji {
+    pub name: String,
+    #[serde(default)]
+    pub deactivated: bool,
+}
+
 /*
 * TODO:
 * BOT RESPONSES TO ZUIP - JSON RESONSES TO ZULIP'S OUTGOING WEBHOOKS
@@ -202,6 +372,24 @@ pub struct OutgoingWebhook {
 * https://zulip.com/help/format-your-message-using-markdown
 */
 
+/// Response of GET /api/v1/users
+#[derive(Deserialize, Debug)]
+struct UsersResponse {
+    result: String,
+    msg: String,
+    #[serde(default)]
+    members: Vec<Member>,
+}
+
+/// A user of the Zulip realm
+#[derive(Deserialize, Debug, Clone)]
+pub struct Member {
+    pub user_id: u64,
+    pub full_name: String,
+    #[serde(default)]
+    pub is_active: bool,
+}
+
 /* -------------------------------------------------------------------------- */
 /*                                  Emoji JSON                                */
 /* -------------------------------------------------------------------------- */